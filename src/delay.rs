@@ -98,7 +98,7 @@ impl DelayCM {
     /// Create a new delay
     pub fn new(clocks: Clocks) -> Self {
         DelayCM {
-            sysclk: clocks.sysclk(),
+            sysclk: Hertz::from(clocks.sysclk()),
         }
     }
 
@@ -30,7 +30,10 @@
 
 #![deny(missing_docs)]
 
-use crate::stm32::{flash, FLASH};
+use crate::pwr::VosRange;
+use crate::rcc::Clocks;
+use crate::stm32::{flash, rcc, FLASH, RCC};
+use crate::time::Hertz;
 use crate::traits::flash as flash_trait;
 use core::convert::TryInto;
 use core::{mem, ops::Drop, ptr};
@@ -119,6 +122,84 @@ generate_register!(PCROP1ER, pcrop1er);
 generate_register!(WRP1AR, wrp1ar);
 generate_register!(WRP1BR, wrp1br);
 
+impl ACR {
+    /// Returns the currently programmed flash latency (`FLASH_ACR.LATENCY`) in wait states
+    pub fn latency(&mut self) -> u8 {
+        self.acr().read().latency().bits()
+    }
+
+    /// Returns the highest HCLK the flash latency `bits` allows in the voltage range `range`
+    ///
+    /// This is the wait state table of the reference manual read the other way round. With the
+    /// AHB prescaler at 1 it is the highest safe SYSCLK as well. Latencies above the largest one
+    /// needed by the range return its maximum clock.
+    pub fn max_sysclk_for_latency(bits: u8, range: VosRange) -> Hertz {
+        match range {
+            VosRange::HighPerformance => match bits {
+                0 => Hertz::MHz(16),
+                1 => Hertz::MHz(32),
+                2 => Hertz::MHz(48),
+                3 => Hertz::MHz(64),
+                _ => Hertz::MHz(80),
+            },
+            VosRange::LowPower => match bits {
+                0 => Hertz::MHz(6),
+                1 => Hertz::MHz(12),
+                2 => Hertz::MHz(18),
+                _ => Hertz::MHz(26),
+            },
+        }
+    }
+
+    /// Returns the lowest flash latency that allows `hclk` in the voltage range `range`, or `None`
+    /// if `hclk` exceeds the maximum of the range
+    pub fn latency_for_hclk(hclk: Hertz, range: VosRange) -> Option<u8> {
+        (0..=4).find(|&bits| hclk <= Self::max_sysclk_for_latency(bits, range))
+    }
+
+    /// Programs the flash latency for switching HCLK to `hclk` in the voltage range `range` and
+    /// returns it
+    ///
+    /// The CPU keeps running on the current HCLK, read back from `RCC_CFGR`, until the switch, so
+    /// the latency programmed is the higher of the ones needed by the current HCLK and by `hclk`.
+    /// Call this before and after raising or lowering HCLK or switching the voltage range; once
+    /// HCLK has been switched, the latency drops to what `hclk` needs. If the current HCLK can not
+    /// be determined, as it is derived from the HSE, the highest latency of the range is kept.
+    ///
+    /// Returns `Error::Illegal` without touching the register if the current HCLK or `hclk`
+    /// exceeds the range, and `Error::Failure` if the latency does not read back as written.
+    pub fn set_wait_states_for(&mut self, hclk: Hertz, range: VosRange) -> Result<u8, Error> {
+        // NOTE(unsafe) only reads the RCC registers
+        let rcc = unsafe { &*RCC::ptr() };
+        Self::set_wait_states(self.acr(), rcc, None, hclk, range)
+    }
+
+    // `set_wait_states_for` on the given registers, `hse` is the frequency of the HSE if known
+    pub(crate) fn set_wait_states(
+        acr: &flash::ACR,
+        rcc: &rcc::RegisterBlock,
+        hse: Option<Hertz>,
+        hclk: Hertz,
+        range: VosRange,
+    ) -> Result<u8, Error> {
+        let current_hclk = Clocks::from_hardware(rcc, acr, hse).map_or_else(
+            || Self::max_sysclk_for_latency(4, range),
+            |clocks| Hertz::from(clocks.hclk()),
+        );
+        let bits = Self::latency_for_hclk(hclk, range)
+            .zip(Self::latency_for_hclk(current_hclk, range))
+            .map(|(new, current)| new.max(current))
+            .ok_or(Error::Illegal)?;
+
+        acr.modify(|_, w| unsafe { w.latency().bits(bits) });
+        if acr.read().latency().bits() != bits {
+            return Err(Error::Failure);
+        }
+
+        Ok(bits)
+    }
+}
+
 const FLASH_KEY1: u32 = 0x4567_0123;
 const FLASH_KEY2: u32 = 0xCDEF_89AB;
 
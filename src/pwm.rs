@@ -196,7 +196,7 @@ macro_rules! advanced_timer {
                     tim.ccmr2_output().modify(|_, w| w.oc4pe().set_bit().oc4m().bits(6));
                 }
 
-                let clk = clocks.pclk2();
+                let clk = Hertz::from(clocks.pclk2());
                 let ticks = clk / freq;
 
                 // maybe this is all u32? also, why no `- 1` vs `timer.rs`?
@@ -264,7 +264,7 @@ macro_rules! standard_timer {
                     tim.ccmr2_output().modify(|_, w| w.oc4pe().set_bit().oc4m().bits(6));
                 }
 
-                let clk = clocks.pclk1();
+                let clk = Hertz::from(clocks.pclk1());
                 let ticks = clk / freq;
 
                 // maybe this is all u32? also, why no `- 1` vs `timer.rs`?
@@ -321,7 +321,7 @@ macro_rules! small_timer {
                 //     tim.ccmr1_output().modify(|_, w| w.oc2pe().set_bit().oc2m().bits(6));
                 // }
 
-                let clk = clocks.pclk1();
+                let clk = Hertz::from(clocks.pclk1());
                 let ticks = clk / freq;
 
                 // maybe this is all u32? also, why no `- 1` vs `timer.rs`?
@@ -1,7 +1,8 @@
 //! Power management
 
-use crate::rcc::{Clocks, Enable, APB1R1};
+use crate::rcc::{Clocks, Enable, APB1R1, MAX_SYSCLK_RANGE1, MAX_SYSCLK_RANGE2};
 use crate::stm32::{pwr, PWR};
+use crate::time::Hertz;
 use bitfield::{bitfield, BitRange};
 use cortex_m::peripheral::SCB;
 use fugit::RateExtU32;
@@ -60,7 +61,7 @@ impl Pwr {
                 Ok(())
             },
             VosRange::LowPower => {
-                if clocks.sysclk() > 26.MHz::<1, 1>() {
+                if Hertz::from(clocks.sysclk()) > MAX_SYSCLK_RANGE2 {
                     Err(Error::SysClkTooHighVos)
                 } else {
                     unsafe {
@@ -74,6 +75,23 @@ impl Pwr {
         }
     }
 
+    /// Returns the voltage range the regulator is currently set to
+    pub fn power_range(&mut self) -> VosRange {
+        if self.cr1.reg().read().vos().bits() == VosRange::LowPower as u8 {
+            VosRange::LowPower
+        } else {
+            VosRange::HighPerformance
+        }
+    }
+
+    /// Returns the highest SYSCLK the current voltage range allows
+    pub fn max_sysclk(&mut self) -> Hertz {
+        match self.power_range() {
+            VosRange::HighPerformance => MAX_SYSCLK_RANGE1,
+            VosRange::LowPower => MAX_SYSCLK_RANGE2,
+        }
+    }
+
     /// Switches the system into low power run mode
     pub fn low_power_run(&mut self, clocks: &Clocks) -> Result<(), Error> {
         if clocks.sysclk() > 2.MHz::<1, 1>() {
@@ -85,7 +103,23 @@ impl Pwr {
     }
 
     /// Enters 'Shutdown' low power mode.
+    ///
+    /// The MCU restarts from reset on wakeup, so the `Clocks` from before are no longer valid and
+    /// the clocks have to be frozen again.
     pub fn shutdown(&mut self, wkup: &WakeUpSource, scb: &mut SCB) -> ! {
+        self.enter_low_power_mode(wkup, scb, 0b111)
+    }
+
+    /// Enters 'Standby' low power mode.
+    ///
+    /// Like for 'Shutdown', the MCU restarts from reset on wakeup and the `Clocks` from before are
+    /// no longer valid. Unlike 'Shutdown', the LSI, the brown-out reset and optionally the SRAM2
+    /// content are kept.
+    pub fn standby(&mut self, wkup: &WakeUpSource, scb: &mut SCB) -> ! {
+        self.enter_low_power_mode(wkup, scb, 0b011)
+    }
+
+    fn enter_low_power_mode(&mut self, wkup: &WakeUpSource, scb: &mut SCB, lpms: u8) -> ! {
         unsafe {
             self.cr3.reg().modify(|_, w| w.bits(wkup.bit_range(0, 7)));
         }
@@ -109,13 +143,13 @@ impl Pwr {
                 .sbf()
                 .set_bit()
         });
-        unsafe { self.cr1.reg().modify(|_, w| w.lpms().bits(0b111)) };
+        unsafe { self.cr1.reg().modify(|_, w| w.lpms().bits(lpms)) };
         cortex_m::asm::dsb();
         cortex_m::asm::wfi();
         loop {}
     }
 
-    /// Returns the reason, why wakeup from shutdown happened. In case there is more then one,
+    /// Returns the reason, why wakeup from shutdown or standby happened. In case there is more then one,
     /// a single random reason will be returned
     pub fn read_wakeup_reason(&mut self) -> WakeUpSource {
         WakeUpSource(self.sr1.reg().read().bits() as u16)
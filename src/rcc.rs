@@ -1,26 +1,219 @@
 //! Reset and Clock Control
 
+mod batch;
+// The L41x and L4+ parts have no bxCAN, see `crate::can`
+#[cfg(not(any(feature = "stm32l412", feature = "stm32l4r9", feature = "stm32l4s9",)))]
+pub mod can;
+pub mod ccipr;
 pub mod cfgr;
 pub mod clocks;
 mod enable;
 pub mod hclk;
 pub mod hse;
+pub mod hsi16;
+// The L47x/L48x parts have no HSI48
+#[cfg(not(any(
+    feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486"
+)))]
+pub mod hsi48;
+pub mod lowpower;
+pub mod mco;
+pub mod measure;
+#[cfg(test)]
+mod mock;
 pub mod msi;
+pub mod path;
 pub mod pclk;
 pub mod pll;
-
-pub use cfgr::CFGR;
-pub use clocks::Clocks;
+// The L41x/L42x parts have no PLLSAI1
+#[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+pub mod pllsai1;
+#[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+pub mod pllsai2;
+pub mod requirements;
+pub mod status;
+pub mod tree;
+pub mod usb;
+
+pub use crate::enable_all;
+pub use batch::{Apb1, Buses, GetBus};
+#[cfg(not(any(feature = "stm32l412", feature = "stm32l4r9", feature = "stm32l4s9",)))]
+pub use can::{CanBitTiming, CanClockError};
+pub use cfgr::{handle_hse_css_failure, CFGR};
+pub use clocks::{ClockDiff, ClockReport, Clocks, Hclk, Pclk1, Pclk2, Sysclk, Timclk1, Timclk2};
 pub use hclk::HclkConfig;
 pub use hse::HseConfig;
-pub use msi::MsiFreq;
+pub use lowpower::{
+    AnalogSleepGating, LowPowerClockPlan, LowPowerMode, MsiStandbyRange, RootClock, StopWakeupClock,
+};
+pub use mco::{McoPrescaler, McoSource};
+pub use measure::CaptureRatio;
+pub use msi::{MsiFreq, MsiTrim};
+pub use requirements::{ClockRequirements, UnmetRequirement};
+pub use status::{oscillator_status, OscStatus};
+pub use tree::{ClockNode, ClockTree, ClockTreeNode};
+pub use usb::UsbClockError;
 
 use crate::pac::rcc::RegisterBlock;
-use crate::stm32::{rcc, RCC};
-use crate::time::Hertz;
-
-pub const MAX_CLOCK_SPEED: Hertz = Hertz::MHz(80);
+use crate::pwr;
+use crate::rtc::RtcClockSource;
+use crate::stm32::{rcc, FLASH, RCC};
+use crate::time::{Hertz, MilliSeconds};
+
+/// Highest SYSCLK in voltage range 1 (high performance)
+pub const MAX_SYSCLK_RANGE1: Hertz = Hertz::MHz(80);
+/// Highest SYSCLK in voltage range 2 (low power)
+pub const MAX_SYSCLK_RANGE2: Hertz = Hertz::MHz(26);
+/// Highest AHB clock
+pub const MAX_HCLK: Hertz = Hertz::MHz(80);
+/// Highest APB1 clock
+pub const MAX_PCLK1: Hertz = Hertz::MHz(80);
+/// Highest APB2 clock
+pub const MAX_PCLK2: Hertz = Hertz::MHz(80);
+pub const MAX_CLOCK_SPEED: Hertz = MAX_SYSCLK_RANGE1;
 const HSI16_FREQ: Hertz = Hertz::MHz(16);
+const LSE_FREQ: Hertz = Hertz::Hz(32_768);
+const LSI_FREQ: Hertz = Hertz::kHz(32);
+
+/// Errors in a clock configuration, detected before any clock is changed
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockError {
+    /// Neither a SYSCLK configuration nor a preferred source has been provided and the MSI, which
+    /// is the fallback, is not enabled
+    NoSysclk,
+    /// The clock selected as SYSCLK source is not enabled
+    SysclkSourceDisabled(SysclkSource),
+    /// The clock feeding SYSCLK does not run at the requested SYSCLK frequency
+    SysclkFreqMismatch,
+    /// The declared HSE frequency is outside of the range of the crystal or bypass mode
+    HseFreqOutOfRange,
+    /// The clock selected as PLL input is not enabled
+    PllSourceDisabled(pll::PllSource),
+    /// The VCO input frequency of a PLL is outside of its allowed range
+    PllVcoInputOutOfRange,
+    /// The VCO output frequency of a PLL is outside of 64 MHz to 344 MHz
+    PllVcoOutOfRange,
+    /// The MSI range the PLL has been configured for differs from the configured MSI range
+    PllMsiRangeMismatch,
+    /// A PLLSAI uses a different source than the main PLL, but all of them share one source
+    PllSourceConflict,
+    /// The PLL parameters do not result in the targeted PLL output frequency
+    PllFreqMismatch,
+    /// The PLL did not lock within the timeout, see `pll::relock`
+    PllLockTimeout,
+    /// PLLSAI1 uses a different input divider than the main PLL, but they share it
+    PllInputDividerConflict,
+    /// The clock selected as 48 MHz clock is not enabled, or the selected PLL has no Q output
+    Clk48SourceDisabled(ccipr::Clk48ClockSource),
+    /// The clock selected as 48 MHz clock does not run at 48 MHz
+    Clk48FreqMismatch,
+    /// The MSI is set up as 48 MHz clock for USB, but there is no LSE to calibrate it
+    Clk48MsiWithoutLse,
+    /// The LSE is selected as LPUART1 kernel clock, but not enabled
+    Lpuart1SourceDisabled,
+    /// The clock selected as DFSDM1 audio clock is not enabled, or SAI1 is selected without
+    /// `CFGR::set_sai1_source`
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    DfsdmAudioSourceDisabled(ccipr::DfsdmAudioClockSource),
+    /// The clock selected for the MCO is not enabled
+    McoSourceDisabled(McoSource),
+    /// The clock selected as RTC clock is not running, see `BDCR::enable_rtc`
+    RtcClockNotRunning(RtcClockSource),
+    /// None of the clocks `CFGR::auto_usart1_source` chooses from can provide the baud rate
+    NoUsart1SourceForBaud,
+    /// HCLK is not SYSCLK divided by an available prescaler
+    InvalidHclkDivider,
+    /// HCLK is SYSCLK divided by 32, the one power of two up to 512 the AHB prescaler lacks
+    HclkDivider32,
+    /// PCLK1 is not HCLK divided by an available prescaler
+    InvalidPclk1Divider,
+    /// PCLK2 is not HCLK divided by an available prescaler
+    InvalidPclk2Divider,
+    /// No APB1 prescaler results in the requested APB1 timer clock
+    InvalidTimclk1Freq,
+    /// No APB2 prescaler results in the requested APB2 timer clock
+    InvalidTimclk2Freq,
+    /// SYSCLK is above `MAX_SYSCLK_RANGE1`
+    SysclkTooHigh,
+    /// SYSCLK is above the limit of the current voltage range, see `Pwr::max_sysclk`
+    SysclkAboveVoltageRange,
+    /// HCLK is above `MAX_HCLK`
+    HclkTooHigh,
+    /// PCLK1 is above `MAX_PCLK1`
+    Pclk1TooHigh,
+    /// PCLK2 is above `MAX_PCLK2`
+    Pclk2TooHigh,
+    /// The APB1 prescaler is below the minimum set with `CFGR::set_pclk1_min_prescaler`
+    Pclk1PrescalerBelowMinimum,
+    /// The APB2 prescaler is below the minimum set with `CFGR::set_pclk2_min_prescaler`
+    Pclk2PrescalerBelowMinimum,
+    /// The clock security system of the LSE uses the LSI as fallback, but the LSI is disabled
+    LseCssWithoutLsi,
+    /// `ClockSecuritySystem::EnableWithoutInterrupt` is selected for the HSE, whose clock security
+    /// system always fires the NMI
+    HseCssWithoutInterrupt,
+    /// The clock to be disabled feeds SYSCLK, directly or through the PLL
+    ClockFeedsSysclk(SysclkSource),
+}
+
+/// Function that is called repeatedly while waiting for a clock to become ready
+///
+/// This allows an RTOS to yield or the core to sleep during the potentially long startup of an
+/// oscillator, instead of busy spinning.
+pub type WaitHook = fn();
+
+// Spins until `ready` returns true, calling the wait hook in between if there is one.
+//
+// All polling of ready bits during `CFGR::freeze` goes through this function or
+// `wait_until_timeout` with the configured wait hook, so the number of polls can be observed from
+// the hook. The host tests rely on this to emulate the hardware, see `mock`.
+fn wait_until(hook: Option<WaitHook>, mut ready: impl FnMut() -> bool) {
+    while !ready() {
+        if let Some(hook) = hook {
+            hook();
+        }
+    }
+}
+
+// Like `wait_until`, but gives up after at least `timeout` has passed at the given core clock.
+// Returns whether `ready` became true.
+fn wait_until_timeout(
+    hook: Option<WaitHook>,
+    timeout: MilliSeconds,
+    sysclk: Hertz,
+    mut ready: impl FnMut() -> bool,
+) -> bool {
+    let cycles_per_ms = sysclk.raw() / 1_000;
+
+    for _ in 0..timeout.ticks() {
+        if ready() {
+            return true;
+        }
+        if let Some(hook) = hook {
+            hook();
+        }
+        cortex_m::asm::delay(cycles_per_ms);
+    }
+
+    ready()
+}
+
+/// Reconstructs the `Clocks` from the RCC registers, e.g. when a bootloader or another stage has
+/// configured the clocks
+///
+/// The HSE frequency can not be measured, so `hse_hint` has to be provided if the HSE is running.
+/// Returns `None` if a running clock can not be determined, i.e. the HSE runs without a hint.
+pub fn read_clocks(hse_hint: Option<Hertz>) -> Option<Clocks> {
+    // NOTE(unsafe) only reads the RCC and FLASH registers
+    let rcc = unsafe { &*RCC::ptr() };
+    let acr = unsafe { &(*FLASH::ptr()).acr };
+
+    Clocks::from_hardware(rcc, acr, hse_hint)
+}
 
 /// Extension trait that constrains the `RCC` peripheral
 pub trait RccExt {
@@ -135,6 +328,34 @@ impl BDCR {
         // NOTE(unsafe) this proxy grants exclusive access to this register
         unsafe { &(*RCC::ptr()).bdcr }
     }
+
+    /// Enables the RTC clock (`RCC_BDCR.RTCEN`), if the clock selected by `RCC_BDCR.RTCSEL` runs
+    ///
+    /// This unlocks the backup domain and can be called any number of times, e.g. after `freeze`
+    /// on every boot. The RTC clock source is selected by `Rtc::new`, which resets the backup
+    /// domain if it changes. After the RTC clock has been enabled, the calendar can only be read
+    /// once it has been synchronized to PCLK1 (`RTC_ISR.RSF`), which takes up to two RTC clock
+    /// periods.
+    pub fn enable_rtc(&mut self, pwrcr1: &mut pwr::CR1) -> Result<(), ClockError> {
+        pwrcr1.reg().modify(|_, w| w.dbp().set_bit());
+        wait_until(None, || pwrcr1.reg().read().dbp().bit_is_set());
+
+        // NOTE(unsafe) atomic reads with no side effects
+        let rcc = unsafe { &*RCC::ptr() };
+        let bdcr = self.enr().read();
+        let (source, running) = match bdcr.rtcsel().bits() {
+            0b01 => (RtcClockSource::LSE, bdcr.lserdy().bit_is_set()),
+            0b10 => (RtcClockSource::LSI, rcc.csr.read().lsirdy().bit_is_set()),
+            0b11 => (RtcClockSource::HSE, rcc.cr.read().hserdy().bit_is_set()),
+            _ => (RtcClockSource::NoClock, false),
+        };
+        if !running {
+            return Err(ClockError::RtcClockNotRunning(source));
+        }
+
+        self.enr().modify(|_, w| w.rtcen().set_bit());
+        Ok(())
+    }
 }
 
 macro_rules! bus_struct {
@@ -167,6 +388,17 @@ macro_rules! bus_struct {
                     // NOTE(unsafe) this proxy grants exclusive access to this register
                     unsafe { &(*RCC::ptr()).$rst }
                 }
+
+                /// Pulses the reset line of every peripheral on this bus
+                ///
+                /// This is meant for bringing the bus into a clean state at boot. Only the reset bits
+                /// of peripherals implementing [`Reset`] are written, reserved bits are left clear.
+                /// The flash interface on AHB1 and PWR on APB1 are not reset, as the clock
+                /// configuration relies on their settings.
+                pub fn reset_all(&mut self) {
+                    self.rstr().write(|w| unsafe { w.bits(enable::RESET_MASKS.$busX) });
+                    self.rstr().reset();
+                }
             }
         )+
     };
@@ -181,6 +413,41 @@ bus_struct! {
     APB2 => (APB2ENR, apb2enr, APB2SMENR, apb2smenr, APB2RSTR, apb2rstr, "Advanced Peripheral Bus 2 (APB2) registers"),
 }
 
+impl AHB1 {
+    /// Keeps the flash interface clocked in Sleep mode, which is the default after reset
+    ///
+    /// In Run mode the flash interface is clocked through the `Enable` implementation of `FLASH`,
+    /// which is also on after reset. `CFGR::freeze` never touches either. Only stop the clock in
+    /// Sleep mode if the code that runs in it, including interrupt handlers, executes from SRAM.
+    pub fn set_flash_clock_in_sleep(&mut self, on: bool) {
+        self.smenr().modify(|_, w| w.flashsmen().bit(on));
+    }
+
+    /// Keeps SRAM1 clocked in Sleep mode, which is the default after reset
+    ///
+    /// SRAM1 is always clocked in Run mode.
+    pub fn set_sram1_clock_in_sleep(&mut self, on: bool) {
+        self.smenr().modify(|_, w| w.sram1smen().bit(on));
+    }
+}
+
+impl AHB2 {
+    /// Keeps SRAM2 clocked in Sleep mode, which is the default after reset
+    ///
+    /// SRAM2 is always clocked in Run mode and can be used right after reset.
+    pub fn set_sram2_clock_in_sleep(&mut self, on: bool) {
+        self.smenr().modify(|_, w| w.sram2smen().bit(on));
+    }
+
+    /// Keeps SRAM3 clocked in Sleep mode, which is the default after reset
+    ///
+    /// SRAM3 is always clocked in Run mode.
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    pub fn set_sram3_clock_in_sleep(&mut self, on: bool) {
+        self.smenr().modify(|_, w| w.sram3smen().bit(on));
+    }
+}
+
 /// Bus associated to peripheral
 pub trait RccBus: crate::Sealed {
     /// Bus type;
@@ -276,17 +543,48 @@ pub enum CrystalBypass {
 pub enum ClockSecuritySystem {
     /// Enable the clock security system to detect clock failures
     Enable,
+    /// Enable the clock security system, but only set the failure flag for polling, see
+    /// `Clocks::lse_failure_detected`
+    ///
+    /// Only the LSE CSS interrupt can be left disabled, the HSE CSS always fires the NMI. The HSE
+    /// configuration is rejected with `ClockError::HseCssWithoutInterrupt`.
+    EnableWithoutInterrupt,
     /// Leave the clock security system disabled
     Disable,
 }
 
+/// Peripherals that use the HSI16 as their kernel clock
+///
+/// Each of these keeps the HSI16 enabled in `CFGR::freeze`, even if it neither drives SYSCLK nor
+/// one of the PLLs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Hsi16Consumers {
+    /// USARTs, UARTs or the LPUART
+    pub usart: bool,
+    /// I2C peripherals
+    pub i2c: bool,
+    /// Low power timers
+    pub lptim: bool,
+    /// Single wire protocol master interface
+    pub swpmi: bool,
+    /// SAIs (L4+ only)
+    pub sai: bool,
+}
+
+impl Hsi16Consumers {
+    /// Returns true if any peripheral uses the HSI16
+    pub fn any(&self) -> bool {
+        self.usart || self.i2c || self.lptim || self.swpmi || self.sai
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct SysclkConfig {
     pub speed: Hertz,
     pub source_clock: SysclkSource,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u8)]
 pub enum SysclkSource {
     MSI = 0b00,
@@ -294,3 +592,33 @@ pub enum SysclkSource {
     HSE = 0b10,
     PLL = 0b11,
 }
+
+impl SysclkSource {
+    pub(crate) fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0b00 => Self::MSI,
+            0b01 => Self::HSI16,
+            0b10 => Self::HSE,
+            _ => Self::PLL,
+        }
+    }
+}
+
+// Refuses to switch off a clock that currently feeds SYSCLK, either as the SYSCLK source
+// (`RCC_CFGR.SWS`) or as the input of the PLL while the PLL is the SYSCLK source. Every helper
+// that disables an oscillator or the PLL goes through this check.
+fn ensure_not_sysclk(rcc: &RegisterBlock, source: SysclkSource) -> Result<(), ClockError> {
+    let active = SysclkSource::from_bits(rcc.cfgr.read().sws().bits());
+    let pll_input = match rcc.pllcfgr.read().pllsrc().bits() {
+        0b01 => Some(SysclkSource::MSI),
+        0b10 => Some(SysclkSource::HSI16),
+        0b11 => Some(SysclkSource::HSE),
+        _ => None,
+    };
+
+    if active == source || (active == SysclkSource::PLL && pll_input == Some(source)) {
+        Err(ClockError::ClockFeedsSysclk(source))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+//! Enabling many peripherals at once, see `enable_all!`, and APB1 as a single logical bus
+
+use super::{Enable, Reset, AHB1, AHB2, AHB3, APB1R1, APB1R2, APB2};
+
+/// Mutable borrows of all bus register proxies of `Rcc`
+///
+/// Borrowing the fields one by one keeps this usable after `Rcc::cfgr` has been moved out by
+/// `freeze`.
+pub struct Buses<'a> {
+    pub ahb1: &'a mut AHB1,
+    pub ahb2: &'a mut AHB2,
+    pub ahb3: &'a mut AHB3,
+    pub apb1r1: &'a mut APB1R1,
+    pub apb1r2: &'a mut APB1R2,
+    pub apb2: &'a mut APB2,
+}
+
+/// Bus register proxy of type `BUS` within `Buses`
+pub trait GetBus<BUS> {
+    fn bus(&mut self) -> &mut BUS;
+}
+
+macro_rules! get_bus {
+    ($($busX:ident => $field:ident,)+) => {
+        $(
+            impl GetBus<$busX> for Buses<'_> {
+                fn bus(&mut self) -> &mut $busX {
+                    self.$field
+                }
+            }
+        )+
+    };
+}
+
+get_bus! {
+    AHB1 => ahb1,
+    AHB2 => ahb2,
+    AHB3 => ahb3,
+    APB1R1 => apb1r1,
+    APB1R2 => apb1r2,
+    APB2 => apb2,
+}
+
+impl Buses<'_> {
+    /// Enables the peripheral `PER` on its bus, including the synchronization of `Enable::enable`
+    pub fn enable<PER>(&mut self)
+    where
+        PER: Enable,
+        Self: GetBus<PER::Bus>,
+    {
+        PER::enable(self.bus());
+    }
+}
+
+/// Both register banks of the APB1, `APB1R1` and `APB1R2`, as one logical bus
+///
+/// Drivers enable and reset their peripheral through this without knowing which of the two
+/// `RCC_APB1xxR1`/`RCC_APB1xxR2` registers it lives in.
+///
+/// ```ignore
+/// let mut apb1 = rcc::Apb1 { r1: &mut rcc.apb1r1, r2: &mut rcc.apb1r2 };
+/// apb1.enable::<pac::LPUART1>();
+/// apb1.enable::<pac::USART2>();
+/// ```
+pub struct Apb1<'a> {
+    pub r1: &'a mut APB1R1,
+    pub r2: &'a mut APB1R2,
+}
+
+impl GetBus<APB1R1> for Apb1<'_> {
+    fn bus(&mut self) -> &mut APB1R1 {
+        self.r1
+    }
+}
+
+impl GetBus<APB1R2> for Apb1<'_> {
+    fn bus(&mut self) -> &mut APB1R2 {
+        self.r2
+    }
+}
+
+impl Apb1<'_> {
+    /// Enables the APB1 peripheral `PER` in whichever register bank it is
+    pub fn enable<PER>(&mut self)
+    where
+        PER: Enable,
+        Self: GetBus<PER::Bus>,
+    {
+        PER::enable(self.bus());
+    }
+
+    /// Disables the APB1 peripheral `PER` in whichever register bank it is
+    pub fn disable<PER>(&mut self)
+    where
+        PER: Enable,
+        Self: GetBus<PER::Bus>,
+    {
+        PER::disable(self.bus());
+    }
+
+    /// Resets the APB1 peripheral `PER` in whichever register bank it is
+    pub fn reset<PER>(&mut self)
+    where
+        PER: Reset,
+        Self: GetBus<PER::Bus>,
+    {
+        PER::reset(self.bus());
+    }
+}
+
+/// Enables all listed peripherals on their buses
+///
+/// ```ignore
+/// let mut rcc = dp.RCC.constrain();
+/// rcc::enable_all!(rcc, [GPIOA, GPIOB, USART2, TIM2]);
+/// ```
+#[macro_export]
+macro_rules! enable_all {
+    ($rcc:expr, [$($PER:ident),* $(,)?]) => {{
+        let mut buses = $crate::rcc::Buses {
+            ahb1: &mut $rcc.ahb1,
+            ahb2: &mut $rcc.ahb2,
+            ahb3: &mut $rcc.ahb3,
+            apb1r1: &mut $rcc.apb1r1,
+            apb1r2: &mut $rcc.apb1r2,
+            apb2: &mut $rcc.apb2,
+        };
+        $(buses.enable::<$crate::pac::$PER>();)*
+    }};
+}
@@ -0,0 +1,150 @@
+//! Kernel clock and bit timing of the bxCAN
+//!
+//! The STM32L4 parts have no FDCAN. The bxCAN always runs on PCLK1, so the bit timing has to be
+//! derived from it.
+
+use crate::time::Hertz;
+
+use super::Clocks;
+
+/// Largest bit rate error accepted by `Clocks::can_bit_timing`, in parts per million
+///
+/// The CAN oscillator tolerance budget is shared by all nodes on the bus, so the error introduced
+/// by the prescaler has to stay well below it.
+pub const MAX_CAN_BITRATE_ERROR_PPM: u32 = 5_000;
+
+/// Sample point aimed for, in per mille of the bit time
+const SAMPLE_POINT: u32 = 875;
+
+/// Reason why PCLK1 can't clock the bxCAN at a bit rate, see `Clocks::can_bit_timing`
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CanClockError {
+    /// The requested bit rate is 0
+    ZeroBitrate,
+    /// PCLK1 is too slow or too fast for any prescaler
+    NoBitTiming,
+    /// The closest bit rate is off by more than `MAX_CAN_BITRATE_ERROR_PPM`
+    BitrateMismatch(u32),
+}
+
+/// Bit timing of the bxCAN
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CanBitTiming {
+    /// Baud rate prescaler, 1 to 1024
+    pub prescaler: u16,
+    /// Time quanta before the sample point, without the sync segment, 1 to 16
+    pub seg1: u8,
+    /// Time quanta after the sample point, 1 to 8
+    pub seg2: u8,
+    /// Resynchronization jump width, 1 to 4
+    pub sjw: u8,
+    /// Bit rate that is actually achieved
+    pub bitrate: u32,
+}
+
+impl CanBitTiming {
+    /// Returns the value of `CAN_BTR`, as taken by `bxcan::CanConfig::set_bit_timing`
+    pub fn btr(&self) -> u32 {
+        (u32::from(self.sjw) - 1) << 24
+            | (u32::from(self.seg2) - 1) << 20
+            | (u32::from(self.seg1) - 1) << 16
+            | (u32::from(self.prescaler) - 1)
+    }
+}
+
+impl Clocks {
+    /// Returns the kernel clock of the bxCAN, which is PCLK1
+    pub fn can_clk(&self) -> Hertz {
+        self.pclk1
+    }
+
+    /// Calculates the bit timing for `bitrate` with a sample point close to 87.5 %
+    ///
+    /// The timing with the most time quanta per bit among those with the smallest bit rate error
+    /// is chosen. PCLK1 should be a multiple of the bit rate, e.g. 40 MHz or 80 MHz for the usual
+    /// bit rates, to get an exact result.
+    pub fn can_bit_timing(&self, bitrate: u32) -> Result<CanBitTiming, CanClockError> {
+        if bitrate == 0 {
+            return Err(CanClockError::ZeroBitrate);
+        }
+
+        // In u64, so large bit rates can't overflow
+        let pclk1 = u64::from(self.pclk1.raw());
+        let bitrate = u64::from(bitrate);
+        let mut best: Option<(u64, CanBitTiming)> = None;
+
+        for quanta in (8..=25u64).rev() {
+            let per_bit = bitrate * quanta;
+            let prescaler = (pclk1 + per_bit / 2) / per_bit;
+            if !(1..=1024).contains(&prescaler) {
+                continue;
+            }
+
+            let actual = pclk1 / (prescaler * quanta);
+            let error = actual.abs_diff(bitrate) * 1_000_000 / bitrate;
+            if matches!(best, Some((best_error, _)) if best_error <= error) {
+                continue;
+            }
+
+            let seg1 = ((quanta * u64::from(SAMPLE_POINT) + 500) / 1000 - 1).min(16);
+            let seg2 = quanta - 1 - seg1;
+            if !(1..=8).contains(&seg2) {
+                continue;
+            }
+
+            let timing = CanBitTiming {
+                prescaler: prescaler as u16,
+                seg1: seg1 as u8,
+                seg2: seg2 as u8,
+                sjw: seg2.min(4) as u8,
+                bitrate: actual as u32,
+            };
+            best = Some((error, timing));
+        }
+
+        match best {
+            None => Err(CanClockError::NoBitTiming),
+            Some((error, timing)) if error > u64::from(MAX_CAN_BITRATE_ERROR_PPM) => {
+                Err(CanClockError::BitrateMismatch(timing.bitrate))
+            }
+            Some((_, timing)) => Ok(timing),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_timing_rejects_zero_bitrate() {
+        assert_eq!(
+            Clocks::default().can_bit_timing(0),
+            Err(CanClockError::ZeroBitrate)
+        );
+    }
+
+    #[test]
+    fn bit_timing_does_not_overflow_on_large_bitrates() {
+        assert_eq!(
+            Clocks::default().can_bit_timing(u32::MAX),
+            Err(CanClockError::NoBitTiming)
+        );
+    }
+
+    #[test]
+    fn bit_timing_is_exact_for_a_multiple_of_the_bitrate() {
+        let clocks = Clocks {
+            pclk1: Hertz::MHz(80),
+            ..Clocks::default()
+        };
+        let timing = clocks.can_bit_timing(500_000).unwrap();
+
+        assert_eq!(timing.bitrate, 500_000);
+        assert_eq!(
+            u32::from(timing.prescaler) * (1 + u32::from(timing.seg1) + u32::from(timing.seg2)),
+            160
+        );
+    }
+}
@@ -0,0 +1,753 @@
+//! Kernel clock selections of the peripherals with an independent clock (`RCC_CCIPR`)
+
+use crate::stm32::RCC;
+use crate::time::Hertz;
+
+use super::{Clocks, LowPowerMode, RootClock, HSI16_FREQ};
+
+macro_rules! clock_source {
+    ($(#[$meta:meta])* $name:ident {
+        $($(#[doc = $doc:literal])* $(#[cfg($cfg:meta)])? $variant:ident = $bits:literal,)+
+    }) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum $name {
+            $($(#[doc = $doc])* $(#[cfg($cfg)])? $variant = $bits,)+
+        }
+
+        impl $name {
+            /// Maps the value of the selection field back to the clock source, if the value is
+            /// not reserved
+            pub fn from_bits(bits: u8) -> Option<Self> {
+                match bits {
+                    $($(#[cfg($cfg)])? $bits => Some(Self::$variant),)+
+                    _ => None,
+                }
+            }
+
+            pub fn bits(self) -> u8 {
+                self as u8
+            }
+        }
+    };
+}
+
+clock_source! {
+    /// Kernel clock source of the USARTs, UARTs and the LPUART
+    UsartClockSource {
+        /// APB clock of the peripheral
+        PCLK = 0b00,
+        /// System clock
+        SYSCLK = 0b01,
+        /// High-speed 16 MHz internal clock
+        HSI16 = 0b10,
+        /// Low-speed 32.768 kHz external clock
+        LSE = 0b11,
+    }
+}
+
+clock_source! {
+    /// Kernel clock source of the I2C peripherals
+    I2cClockSource {
+        /// APB1 clock
+        PCLK = 0b00,
+        /// System clock
+        SYSCLK = 0b01,
+        /// High-speed 16 MHz internal clock
+        HSI16 = 0b10,
+    }
+}
+
+clock_source! {
+    /// Kernel clock source of the low power timers
+    LptimClockSource {
+        /// APB1 clock
+        PCLK = 0b00,
+        /// Low-speed internal clock
+        LSI = 0b01,
+        /// High-speed 16 MHz internal clock
+        HSI16 = 0b10,
+        /// Low-speed 32.768 kHz external clock
+        LSE = 0b11,
+    }
+}
+
+clock_source! {
+    /// Kernel clock source of the SAIs
+    SaiClockSource {
+        /// P output of PLLSAI1
+        PLLSAI1P = 0b000,
+        /// P output of PLLSAI2
+        #[cfg(any(
+            feature = "stm32l471",
+            feature = "stm32l475",
+            feature = "stm32l476",
+            feature = "stm32l486",
+            feature = "stm32l496",
+            feature = "stm32l4a6",
+            feature = "stm32l4r9",
+            feature = "stm32l4s9",
+        ))]
+        PLLSAI2P = 0b001,
+        /// P output of the main PLL
+        PLLP = 0b010,
+        /// External clock on the SAI_EXTCLK pin
+        EXTCLK = 0b011,
+        /// High-speed 16 MHz internal clock
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        HSI16 = 0b100,
+    }
+}
+
+clock_source! {
+    /// Source of the 48 MHz clock used by USB, RNG and SDMMC
+    Clk48ClockSource {
+        /// 48 MHz internal clock, no clock on the L47x/L48x parts
+        #[cfg(not(any(
+            feature = "stm32l471",
+            feature = "stm32l475",
+            feature = "stm32l476",
+            feature = "stm32l486"
+        )))]
+        HSI48 = 0b00,
+        /// Q output of PLLSAI1
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        PLLSAI1Q = 0b01,
+        /// Q output of the main PLL
+        PLLQ = 0b10,
+        /// Multi-speed internal clock
+        MSI = 0b11,
+    }
+}
+
+clock_source! {
+    /// Kernel clock source of the ADCs
+    AdcClockSource {
+        /// No clock selected
+        NoClock = 0b00,
+        /// R output of PLLSAI1
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        PLLSAI1R = 0b01,
+        /// R output of PLLSAI2
+        #[cfg(any(
+            feature = "stm32l471",
+            feature = "stm32l475",
+            feature = "stm32l476",
+            feature = "stm32l486",
+            feature = "stm32l496",
+            feature = "stm32l4a6",
+            feature = "stm32l4r9",
+            feature = "stm32l4s9",
+        ))]
+        PLLSAI2R = 0b10,
+        /// System clock
+        SYSCLK = 0b11,
+    }
+}
+
+clock_source! {
+    /// Kernel clock source of the single wire protocol master interface
+    SwpmiClockSource {
+        /// APB1 clock
+        PCLK = 0b0,
+        /// High-speed 16 MHz internal clock
+        HSI16 = 0b1,
+    }
+}
+
+clock_source! {
+    /// Kernel clock source of the digital filter for sigma delta modulators
+    DfsdmClockSource {
+        /// APB2 clock
+        PCLK = 0b0,
+        /// System clock
+        SYSCLK = 0b1,
+    }
+}
+
+#[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+clock_source! {
+    /// Audio clock source of the digital filter for sigma delta modulators, which clocks the
+    /// serial audio interfaces of its channels
+    DfsdmAudioClockSource {
+        /// SAI1 kernel clock
+        SAI1 = 0b00,
+        /// High-speed 16 MHz internal clock
+        HSI16 = 0b01,
+        /// Multi-speed internal clock
+        MSI = 0b10,
+    }
+}
+
+impl UsartClockSource {
+    /// Returns the resulting kernel clock, if it is running and known
+    pub fn freq(self, clocks: &Clocks, pclk: Hertz) -> Option<Hertz> {
+        match self {
+            Self::PCLK => Some(pclk),
+            Self::SYSCLK => Some(clocks.sysclk),
+            Self::HSI16 => clocks.hsi16().then_some(HSI16_FREQ),
+            Self::LSE => clocks.lse_hz(),
+        }
+    }
+
+    /// Returns true if the source keeps running in Stop mode, or is started on demand by a
+    /// wakeup event, like the HSI16
+    pub fn runs_in_stop(self, clocks: &Clocks) -> bool {
+        match self {
+            Self::HSI16 => true,
+            Self::LSE => clocks.survives_stop(RootClock::LSE),
+            Self::PCLK | Self::SYSCLK => false,
+        }
+    }
+}
+
+/// Computes the `USART_BRR` value for `baud` with 16 times oversampling and the resulting baud rate
+/// error in ppm
+///
+/// The divisor is truncated the way the serial driver does it. Returns `None` if the kernel clock
+/// is too slow or too fast for the baud rate. This does not apply to the LPUART, which divides by
+/// 256 times the kernel clock over the baud rate.
+pub fn usart_baud_error(kernel_hz: Hertz, baud: u32) -> Option<(u16, f32)> {
+    if baud == 0 {
+        return None;
+    }
+    let brr = kernel_hz.raw() / baud;
+    if !(16..=u16::MAX as u32).contains(&brr) {
+        return None;
+    }
+
+    let actual = kernel_hz.raw() as f32 / brr as f32;
+    let error_ppm = (actual - baud as f32) / baud as f32 * 1_000_000.0;
+
+    Some((brr as u16, error_ppm))
+}
+
+impl I2cClockSource {
+    /// Returns the resulting kernel clock, if it is running and known
+    pub fn freq(self, clocks: &Clocks) -> Option<Hertz> {
+        match self {
+            Self::PCLK => Some(clocks.pclk1),
+            Self::SYSCLK => Some(clocks.sysclk),
+            Self::HSI16 => clocks.hsi16().then_some(HSI16_FREQ),
+        }
+    }
+
+    /// Returns true if the source is started on demand by an address match in Stop mode, which
+    /// only the HSI16 is
+    pub fn runs_in_stop(self) -> bool {
+        self == Self::HSI16
+    }
+}
+
+impl LptimClockSource {
+    /// Returns the resulting kernel clock, if it is running and known
+    pub fn freq(self, clocks: &Clocks) -> Option<Hertz> {
+        match self {
+            Self::PCLK => Some(clocks.pclk1),
+            Self::LSI => clocks.lsi_hz(),
+            Self::HSI16 => clocks.hsi16().then_some(HSI16_FREQ),
+            Self::LSE => clocks.lse_hz(),
+        }
+    }
+
+    /// Returns true if the source keeps running in Stop mode, which the low-speed clocks do and the
+    /// HSI16 if it is kept on for the peripherals
+    pub fn runs_in_stop(self, clocks: &Clocks) -> bool {
+        match self {
+            Self::LSI => clocks.survives_stop(RootClock::LSI),
+            Self::LSE => clocks.survives_stop(RootClock::LSE),
+            Self::HSI16 => clocks.survives_stop(RootClock::HSI16),
+            Self::PCLK => false,
+        }
+    }
+}
+
+impl SaiClockSource {
+    /// Returns the resulting kernel clock, if it is running and known
+    ///
+    /// The frequency of the external clock is known if it has been recorded with
+    /// `CFGR::set_sai_ext_clk_freq`. The PLL P outputs are not tracked.
+    pub fn freq(self, clocks: &Clocks) -> Option<Hertz> {
+        match self {
+            Self::EXTCLK => clocks.sai_ext_clk,
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            Self::HSI16 => clocks.hsi16().then_some(HSI16_FREQ),
+            _ => None,
+        }
+    }
+}
+
+impl Clk48ClockSource {
+    /// Returns the resulting 48 MHz domain clock, if it is running and known
+    pub fn freq(self, clocks: &Clocks) -> Option<Hertz> {
+        match self {
+            #[cfg(not(any(
+                feature = "stm32l471",
+                feature = "stm32l475",
+                feature = "stm32l476",
+                feature = "stm32l486"
+            )))]
+            Self::HSI48 => clocks.hsi48().then(|| Hertz::MHz(48)),
+            Self::MSI => clocks.msi().map(|msi| msi.to_hertz()),
+            #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+            Self::PLLSAI1Q => clocks.pllsai1_q,
+            Self::PLLQ => clocks.pll_q,
+        }
+    }
+}
+
+impl AdcClockSource {
+    /// Returns the resulting kernel clock, if it is running and known
+    pub fn freq(self, clocks: &Clocks) -> Option<Hertz> {
+        match self {
+            Self::SYSCLK => Some(clocks.sysclk),
+            // The PLLSAI R outputs are not tracked by `Clocks`
+            _ => None,
+        }
+    }
+}
+
+impl SwpmiClockSource {
+    /// Returns the resulting kernel clock, if it is running and known
+    pub fn freq(self, clocks: &Clocks) -> Option<Hertz> {
+        match self {
+            Self::PCLK => Some(clocks.pclk1),
+            Self::HSI16 => clocks.hsi16().then_some(HSI16_FREQ),
+        }
+    }
+}
+
+impl DfsdmClockSource {
+    /// Returns the resulting kernel clock, if it is running and known
+    pub fn freq(self, clocks: &Clocks) -> Option<Hertz> {
+        match self {
+            Self::PCLK => Some(clocks.pclk2),
+            Self::SYSCLK => Some(clocks.sysclk),
+        }
+    }
+}
+
+#[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+impl DfsdmAudioClockSource {
+    /// Returns the resulting audio clock, if it is running and known
+    pub fn freq(self, clocks: &Clocks) -> Option<Hertz> {
+        match self {
+            Self::SAI1 => clocks.sai1_clk(),
+            Self::HSI16 => clocks.hsi16().then_some(HSI16_FREQ),
+            Self::MSI => clocks.msi().map(|msi| msi.to_hertz()),
+        }
+    }
+}
+
+/// Clock source selected for a peripheral, together with the resulting kernel clock
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KernelClock<S> {
+    /// Selected clock source. `None` if the selection field holds a reserved value
+    pub source: Option<S>,
+    /// Kernel clock frequency. `None` if the source is not running or its frequency is unknown
+    pub freq: Option<Hertz>,
+}
+
+impl<S: Copy> KernelClock<S> {
+    fn new(source: Option<S>, freq: impl FnOnce(S) -> Option<Hertz>) -> Self {
+        Self {
+            source,
+            freq: source.and_then(freq),
+        }
+    }
+}
+
+/// All peripheral kernel clock selections, as currently configured in hardware
+#[derive(Clone, Copy, Debug)]
+pub struct CciprSnapshot {
+    pub usart1: KernelClock<UsartClockSource>,
+    pub usart2: KernelClock<UsartClockSource>,
+    pub usart3: KernelClock<UsartClockSource>,
+    #[cfg(not(any(feature = "stm32l433", feature = "stm32l443")))]
+    pub uart4: KernelClock<UsartClockSource>,
+    #[cfg(any(
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    pub uart5: KernelClock<UsartClockSource>,
+    pub lpuart1: KernelClock<UsartClockSource>,
+    pub i2c1: KernelClock<I2cClockSource>,
+    pub i2c2: KernelClock<I2cClockSource>,
+    pub i2c3: KernelClock<I2cClockSource>,
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    pub i2c4: KernelClock<I2cClockSource>,
+    pub lptim1: KernelClock<LptimClockSource>,
+    pub lptim2: KernelClock<LptimClockSource>,
+    pub sai1: KernelClock<SaiClockSource>,
+    #[cfg(any(
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    pub sai2: KernelClock<SaiClockSource>,
+    pub clk48: KernelClock<Clk48ClockSource>,
+    pub adc: KernelClock<AdcClockSource>,
+    #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9",)))]
+    pub swpmi1: KernelClock<SwpmiClockSource>,
+    #[cfg(any(
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    pub dfsdm1: KernelClock<DfsdmClockSource>,
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    pub dfsdm1_audio: KernelClock<DfsdmAudioClockSource>,
+}
+
+impl CciprSnapshot {
+    /// Reads the current kernel clock selections and resolves them against `clocks`
+    pub fn read(clocks: &Clocks) -> Self {
+        // NOTE(unsafe) atomic read with no side effects
+        let rcc = unsafe { &*RCC::ptr() };
+        let ccipr = rcc.ccipr.read();
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        let ccipr2 = rcc.ccipr2.read();
+
+        let usart = |bits: u8, pclk: Hertz| {
+            KernelClock::new(UsartClockSource::from_bits(bits), |source| {
+                source.freq(clocks, pclk)
+            })
+        };
+        let i2c = |bits: u8| {
+            KernelClock::new(I2cClockSource::from_bits(bits), |source| {
+                source.freq(clocks)
+            })
+        };
+        let lptim = |bits: u8| {
+            KernelClock::new(LptimClockSource::from_bits(bits), |source| {
+                source.freq(clocks)
+            })
+        };
+        let sai = |bits: u8| {
+            KernelClock::new(SaiClockSource::from_bits(bits), |source| {
+                source.freq(clocks)
+            })
+        };
+
+        Self {
+            usart1: usart(ccipr.usart1sel().bits(), clocks.pclk2),
+            usart2: usart(ccipr.usart2sel().bits(), clocks.pclk1),
+            usart3: usart(ccipr.usart3sel().bits(), clocks.pclk1),
+            #[cfg(any(
+                feature = "stm32l431",
+                feature = "stm32l451",
+                feature = "stm32l471",
+                feature = "stm32l412",
+                feature = "stm32l422",
+                feature = "stm32l432",
+                feature = "stm32l442",
+                feature = "stm32l452",
+                feature = "stm32l462",
+            ))]
+            uart4: usart(ccipr.usart4sel().bits(), clocks.pclk1),
+            #[cfg(any(
+                feature = "stm32l475",
+                feature = "stm32l476",
+                feature = "stm32l486",
+                feature = "stm32l496",
+                feature = "stm32l4a6",
+                feature = "stm32l4r9",
+                feature = "stm32l4s9",
+            ))]
+            uart4: usart(ccipr.uart4sel().bits(), clocks.pclk1),
+            #[cfg(any(
+                feature = "stm32l475",
+                feature = "stm32l476",
+                feature = "stm32l486",
+                feature = "stm32l496",
+                feature = "stm32l4a6",
+                feature = "stm32l4r9",
+                feature = "stm32l4s9",
+            ))]
+            uart5: usart(ccipr.uart5sel().bits(), clocks.pclk1),
+            lpuart1: usart(ccipr.lpuart1sel().bits(), clocks.pclk1),
+            i2c1: i2c(ccipr.i2c1sel().bits()),
+            i2c2: i2c(ccipr.i2c2sel().bits()),
+            i2c3: i2c(ccipr.i2c3sel().bits()),
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            i2c4: i2c(ccipr2.i2c4sel().bits()),
+            lptim1: lptim(ccipr.lptim1sel().bits()),
+            lptim2: lptim(ccipr.lptim2sel().bits()),
+            #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9",)))]
+            sai1: sai(ccipr.sai1sel().bits()),
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            sai1: sai(ccipr2.sai1sel().bits()),
+            #[cfg(any(
+                feature = "stm32l475",
+                feature = "stm32l476",
+                feature = "stm32l486",
+                feature = "stm32l496",
+                feature = "stm32l4a6",
+            ))]
+            sai2: sai(ccipr.sai2sel().bits()),
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            sai2: sai(ccipr2.sai2sel().bits()),
+            clk48: KernelClock::new(
+                Clk48ClockSource::from_bits(ccipr.clk48sel().bits()),
+                |source| source.freq(clocks),
+            ),
+            adc: KernelClock::new(AdcClockSource::from_bits(ccipr.adcsel().bits()), |source| {
+                source.freq(clocks)
+            }),
+            #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9",)))]
+            swpmi1: KernelClock::new(
+                SwpmiClockSource::from_bits(ccipr.swpmi1sel().bit() as u8),
+                |source| source.freq(clocks),
+            ),
+            #[cfg(any(
+                feature = "stm32l475",
+                feature = "stm32l476",
+                feature = "stm32l486",
+                feature = "stm32l496",
+                feature = "stm32l4a6",
+            ))]
+            dfsdm1: KernelClock::new(
+                DfsdmClockSource::from_bits(ccipr.dfsdmsel().bit() as u8),
+                |source| source.freq(clocks),
+            ),
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            dfsdm1: KernelClock::new(
+                DfsdmClockSource::from_bits(ccipr2.dfsdmsel().bit() as u8),
+                |source| source.freq(clocks),
+            ),
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            dfsdm1_audio: KernelClock::new(
+                DfsdmAudioClockSource::from_bits(ccipr2.adfsdmsel().bits()),
+                |source| source.freq(clocks),
+            ),
+        }
+    }
+}
+
+impl Clocks {
+    /// Returns the kernel clock of the LPUART1, which the baud rate divisor is computed against
+    pub fn lpuart1_clk(&self) -> Option<Hertz> {
+        self.ccipr_snapshot().lpuart1.freq
+    }
+
+    /// Returns the highest baud rate the LPUART1 kernel clock allows, a third of it
+    pub fn lpuart1_max_baud(&self) -> Option<u32> {
+        self.lpuart1_clk().map(|clk| clk.raw() / 3)
+    }
+
+    /// Returns the kernel clock of the SAI1, if it is known
+    pub fn sai1_clk(&self) -> Option<Hertz> {
+        self.ccipr_snapshot().sai1.freq
+    }
+
+    /// Returns the kernel clock of the SAI2, if it is known
+    #[cfg(any(
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    pub fn sai2_clk(&self) -> Option<Hertz> {
+        self.ccipr_snapshot().sai2.freq
+    }
+
+    /// Returns the audio clock of the DFSDM1, if it is known
+    ///
+    /// The L4R/L4S parts select it with `RCC_CCIPR2.ADFSDMSEL`, on the others it is always the
+    /// SAI1 kernel clock.
+    #[cfg(any(
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    pub fn dfsdm1_audio_clk(&self) -> Option<Hertz> {
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        return self.ccipr_snapshot().dfsdm1_audio.freq;
+        #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9",)))]
+        return self.sai1_clk();
+    }
+}
+
+/// Peripherals that can wake the MCU from Stop mode, see `Clocks::can_wake_from_stop`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopWakeupPeripheral {
+    Usart1,
+    Usart2,
+    Usart3,
+    Lpuart1,
+    I2c1,
+    I2c2,
+    I2c3,
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    I2c4,
+    Lptim1,
+    Lptim2,
+}
+
+/// Low power timer, see `Clocks::lptim_stop_wakeup_ok`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lptim {
+    Lptim1,
+    Lptim2,
+}
+
+impl Clocks {
+    /// Returns true if the kernel clock currently selected for `peripheral` survives Stop mode, so
+    /// the peripheral can wake the MCU
+    ///
+    /// The LSE and LSI are in the backup and low-power domains and keep running in Stop mode. The
+    /// HSI16 is woken up on demand by USARTs, the LPUART and I2Cs, and runs for the LPTIMs only if
+    /// it is kept on. The APB clocks and SYSCLK are stopped. See `survives_stop`.
+    pub fn can_wake_from_stop(&self, peripheral: StopWakeupPeripheral) -> bool {
+        let ccipr = self.ccipr_snapshot();
+        let usart = |clock: KernelClock<UsartClockSource>| match clock.source {
+            Some(source) => source.runs_in_stop(self),
+            None => false,
+        };
+        let i2c = |clock: KernelClock<I2cClockSource>| match clock.source {
+            Some(source) => source.runs_in_stop(),
+            None => false,
+        };
+        let lptim = |clock: KernelClock<LptimClockSource>| match clock.source {
+            Some(source) => source.runs_in_stop(self),
+            None => false,
+        };
+
+        match peripheral {
+            StopWakeupPeripheral::Usart1 => usart(ccipr.usart1),
+            StopWakeupPeripheral::Usart2 => usart(ccipr.usart2),
+            StopWakeupPeripheral::Usart3 => usart(ccipr.usart3),
+            StopWakeupPeripheral::Lpuart1 => usart(ccipr.lpuart1),
+            StopWakeupPeripheral::I2c1 => i2c(ccipr.i2c1),
+            StopWakeupPeripheral::I2c2 => i2c(ccipr.i2c2),
+            StopWakeupPeripheral::I2c3 => i2c(ccipr.i2c3),
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            StopWakeupPeripheral::I2c4 => i2c(ccipr.i2c4),
+            StopWakeupPeripheral::Lptim1 => lptim(ccipr.lptim1),
+            StopWakeupPeripheral::Lptim2 => lptim(ccipr.lptim2),
+        }
+    }
+
+    /// Returns true if `lptim` can wake the MCU from `mode` with its current kernel clock
+    ///
+    /// Besides a kernel clock that survives Stop, see `can_wake_from_stop`, this needs a mode that
+    /// keeps the timer powered: LPTIM1 runs in all Stop modes, LPTIM2 only in Stop 0 and 1. Both
+    /// are off in Standby and Shutdown, where only the RTC and the wakeup pins remain.
+    pub fn lptim_stop_wakeup_ok(&self, lptim: Lptim, mode: LowPowerMode) -> bool {
+        let peripheral = match lptim {
+            Lptim::Lptim1 => StopWakeupPeripheral::Lptim1,
+            Lptim::Lptim2 => StopWakeupPeripheral::Lptim2,
+        };
+
+        peripheral.powered_in(mode) && self.can_wake_from_stop(peripheral)
+    }
+
+    /// Checks that all of `peripherals` keep their kernel clock and power in `mode`
+    ///
+    /// This is the pre-flight check before entering a low-power mode: it combines
+    /// `StopWakeupPeripheral::powered_in` and `can_wake_from_stop` and reports every peripheral
+    /// that would stop, instead of only the first one.
+    pub fn validate_for_mode(
+        &self,
+        mode: LowPowerMode,
+        peripherals: &[StopWakeupPeripheral],
+    ) -> Result<(), StoppedPeripherals> {
+        let mut stopped = StoppedPeripherals::default();
+        for &peripheral in peripherals {
+            if !peripheral.powered_in(mode) || !self.can_wake_from_stop(peripheral) {
+                stopped.insert(peripheral);
+            }
+        }
+
+        if stopped.is_empty() {
+            Ok(())
+        } else {
+            Err(stopped)
+        }
+    }
+}
+
+impl StopWakeupPeripheral {
+    /// Returns true if the peripheral stays powered and functional in `mode`
+    ///
+    /// All of them do in Stop 0 and 1. Only the LPUART1, I2C3 and LPTIM1 remain in Stop 2, and
+    /// none in Standby and Shutdown.
+    pub fn powered_in(self, mode: LowPowerMode) -> bool {
+        match mode {
+            LowPowerMode::Stop0 | LowPowerMode::Stop1 => true,
+            LowPowerMode::Stop2 => matches!(self, Self::Lpuart1 | Self::I2c3 | Self::Lptim1),
+            LowPowerMode::Standby | LowPowerMode::Shutdown => false,
+        }
+    }
+}
+
+/// Peripherals that would stop in a low-power mode, see `Clocks::validate_for_mode`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StoppedPeripherals {
+    mask: u16,
+}
+
+impl StoppedPeripherals {
+    fn insert(&mut self, peripheral: StopWakeupPeripheral) {
+        self.mask |= 1 << peripheral as u16;
+    }
+
+    /// Returns true if `peripheral` would stop
+    pub fn contains(&self, peripheral: StopWakeupPeripheral) -> bool {
+        self.mask & (1 << peripheral as u16) != 0
+    }
+
+    /// Returns the number of peripherals that would stop
+    pub fn len(&self) -> usize {
+        self.mask.count_ones() as usize
+    }
+
+    /// Returns true if no peripheral would stop
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usart_baud_error_truncates_divisor() {
+        // 80 MHz / 115200 = 694.4, truncated to 694, which runs 640 ppm fast
+        let (brr, error_ppm) = usart_baud_error(Hertz::MHz(80), 115_200).unwrap();
+        assert_eq!(brr, 694);
+        assert!((error_ppm - 640.5).abs() < 1.0);
+
+        assert_eq!(usart_baud_error(Hertz::MHz(16), 1_000_000), Some((16, 0.0)));
+    }
+
+    #[test]
+    fn usart_baud_error_rejects_unreachable_baud_rates() {
+        assert_eq!(usart_baud_error(Hertz::MHz(80), 0), None);
+        // The LSE is below the minimum divisor of 16
+        assert_eq!(usart_baud_error(Hertz::Hz(32_768), 9_600), None);
+        // Above the 16 bit BRR
+        assert_eq!(usart_baud_error(Hertz::MHz(80), 1_000), None);
+    }
+}
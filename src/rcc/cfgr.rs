@@ -1,15 +1,37 @@
-use crate::rcc::{RegisterBlock, RCC};
-use crate::time::Hertz;
-use crate::{flash::ACR, pwr::Pwr};
+use crate::rcc::{Enable, RegisterBlock, RCC};
+use crate::rtc::RtcClockSource;
+use crate::stm32::{flash, pwr, FLASH, PWR};
+use crate::time::{Hertz, MicroSeconds, MilliSeconds};
+use crate::{
+    flash::ACR,
+    pwr::{Pwr, VosRange},
+};
 
-use super::pclk::{Pclk1Config, Pclk2Config};
-use super::MsiFreq;
+#[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+use super::ccipr::DfsdmAudioClockSource;
+use super::ccipr::{usart_baud_error, Clk48ClockSource, SaiClockSource, UsartClockSource};
+use super::hclk::HclkDivider;
+#[cfg(not(any(
+    feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486"
+)))]
+use super::hsi48;
+use super::mco::{McoPrescaler, McoSource};
+use super::pclk::{Pclk1Config, Pclk2Config, Prescaler};
+#[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+use super::pllsai1::PllSai1Config;
+#[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+use super::pllsai2::PllSai2Config;
 use super::{
-    pll::{PllConfig, PllOutputDivider, PllSource},
-    LseConfig, SysclkSource,
+    ensure_not_sysclk, wait_until, wait_until_timeout, ClockError, ClockSecuritySystem, Clocks,
+    CrystalBypass, HclkConfig, HseConfig, Hsi16Consumers, SysclkConfig, WaitHook, HSI16_FREQ,
 };
+use super::{msi, AnalogSleepGating, LowPowerClockPlan, MsiFreq};
 use super::{
-    ClockSecuritySystem, Clocks, CrystalBypass, HclkConfig, HseConfig, SysclkConfig, HSI16_FREQ,
+    pll::{self, PllConfig, PllOutputDivider, PllSource},
+    LseConfig, SysclkSource, MAX_HCLK, MAX_PCLK1, MAX_PCLK2, MAX_SYSCLK_RANGE1,
 };
 
 /// Clock configuration to set clock settings or reconfigure them.
@@ -17,14 +39,46 @@ pub struct CFGR {
     hse: Option<HseConfig>,
     lse: Option<LseConfig>,
     msi: Option<MsiFreq>,
+    msi_trim: i8,
     hsi48_on: bool,
     hsi16_on: bool,
+    hsi16_consumers: Hsi16Consumers,
+    hsi16_in_stop: bool,
+    hsi16_wakeup_start: bool,
     lsi_on: bool,
     hclk: Option<HclkConfig>,
     pclk1: Option<Pclk1Config>,
     pclk2: Option<Pclk2Config>,
+    timclk1: Option<Hertz>,
+    timclk2: Option<Hertz>,
+    pclk1_min_prescaler: Prescaler,
+    pclk2_min_prescaler: Prescaler,
     sysclk: Option<SysclkConfig>,
+    sysclk_preference: Option<SysclkSource>,
     pll: Option<PllConfig>,
+    pll_input_divider: Option<u8>,
+    assume_current: bool,
+    skip_reset: bool,
+    hclk_ramp_steps: u8,
+    wait_hook: Option<WaitHook>,
+    lse_timeout: Option<MilliSeconds>,
+    #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+    pllsai1: Option<PllSai1Config>,
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    pllsai2: Option<PllSai2Config>,
+    clk48_source: Option<Clk48ClockSource>,
+    lpuart1_source: Option<UsartClockSource>,
+    sai1_source: Option<SaiClockSource>,
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    dfsdm1_audio_source: Option<DfsdmAudioClockSource>,
+    mco: Option<(McoSource, McoPrescaler)>,
+    sai_ext_clk: Option<Hertz>,
+    usart1_target_baud: Option<u32>,
+    // The MSI feeds USB and has to be calibrated by the LSE
+    usb_on_msi: bool,
+    keep_msi: bool,
+    low_power_plan: LowPowerClockPlan,
+    analog_sleep_gating: Option<AnalogSleepGating>,
 }
 
 impl CFGR {
@@ -38,9 +92,6 @@ impl CFGR {
         self.hse = Some(HseConfig::new(freq, bypass, css));
         self
     }
-    pub(super) fn hse(&self) -> Option<&HseConfig> {
-        self.hse.as_ref()
-    }
 
     /// Add an 32.768 kHz LSE to the system
     pub fn enable_lse(mut self, bypass: CrystalBypass, css: ClockSecuritySystem) -> Self {
@@ -49,30 +100,115 @@ impl CFGR {
         self
     }
 
+    /// Gives up on the LSE if it is not ready within `timeout` and falls back to the LSI
+    ///
+    /// Without a timeout `freeze` waits for the LSE forever. On fallback the LSI is enabled, the
+    /// RTC is moved from the LSE to the LSI (which resets the backup domain) and
+    /// `Clocks::lse_fallback` is set.
+    pub fn set_lse_timeout(mut self, timeout: MilliSeconds) -> Self {
+        self.lse_timeout = Some(timeout);
+        self
+    }
+
     /// Sets a frequency for the AHB bus
     pub fn set_hclk_freq(mut self, freq: Hertz) -> Self {
         self.hclk = Some(HclkConfig::new(freq));
         self
     }
 
-    /// Enable the 48 MHz USB, RNG, SDMMC HSI clock source. Not available on all stm32l4x6 series
+    /// Enable the 48 MHz USB, RNG, SDMMC HSI clock source
+    ///
+    /// Not available on the L47x/L48x parts, which have no HSI48.
+    #[cfg(not(any(
+        feature = "stm32l471",
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486"
+    )))]
     pub fn enable_hsi48(mut self, on: bool) -> Self {
         self.hsi48_on = on;
         self
     }
 
+    /// Enables the 16 MHz HSI
+    ///
+    /// HSI16 is enabled automatically if it is the PLL source. In that case it can't be switched
+    /// off once the PLL is locked, as the PLL keeps depending on its reference clock and the
+    /// hardware forces HSI16 on while it drives SYSCLK, directly or through the PLL.
+    ///
+    /// Without this, `freeze` switches the HSI16 off unless SYSCLK, the PLLs or one of the
+    /// `Hsi16Consumers` need it, even if it has been enabled outside of `CFGR`. Keep it enabled
+    /// here if something else, e.g. a peripheral set up by hand, relies on it.
     pub fn enable_hsi16(mut self, on: bool) -> Self {
         self.hsi16_on = on;
         self
     }
 
+    /// Records the peripherals that will use the HSI16 as kernel clock
+    ///
+    /// The HSI16 is enabled if any of them is set and is not switched off as unused by `freeze`.
+    pub fn set_hsi16_consumers(mut self, consumers: Hsi16Consumers) -> Self {
+        self.hsi16_consumers = consumers;
+        self
+    }
+
+    /// Keeps the HSI16 running in Stop mode for the peripherals using it as kernel clock
+    /// (`RCC_CR.HSIKERON`)
+    ///
+    /// This enables the HSI16 and avoids its startup time when e.g. a USART or I2C wakes up the
+    /// MCU. The ADCs can't use it, they can't be clocked from the HSI16 and don't convert in Stop.
+    pub fn keep_hsi16_in_stop(mut self, on: bool) -> Self {
+        self.hsi16_in_stop = on;
+        self
+    }
+
+    /// Starts the HSI16 in parallel to the MSI when waking up from Stop with the MSI as wakeup
+    /// clock (`RCC_CR.HSIASFS`)
+    pub fn start_hsi16_on_stop_wakeup(mut self, on: bool) -> Self {
+        self.hsi16_wakeup_start = on;
+        self
+    }
+
     /// Enables the MSI with the specified speed
     pub fn enable_msi(mut self, range: MsiFreq) -> Self {
         self.msi = Some(range);
         self
     }
-    pub(super) fn msi(&self) -> Option<MsiFreq> {
-        self.msi
+
+    /// Keeps the MSI running after `freeze`, even if nothing uses it
+    ///
+    /// By default `freeze` switches the MSI off unless it drives SYSCLK, feeds a PLL or is the
+    /// 48 MHz clock. It doesn't have to run to be the wakeup clock from Stop, the hardware
+    /// restarts it.
+    pub fn keep_msi_running(mut self, keep: bool) -> Self {
+        self.keep_msi = keep;
+        self
+    }
+
+    /// Sets the MSI trim value, which is added to the factory calibration
+    ///
+    /// `MsiFreq::trimmed_for` computes the range and trim for frequencies between the MSI ranges.
+    pub fn set_msi_trim(mut self, trim: i8) -> Self {
+        self.msi_trim = trim;
+        self
+    }
+
+    /// Sets the clocks the MCU runs on after a wakeup from Stop and Standby
+    ///
+    /// If the HSI16 is chosen for Stop, it is not kept running during Stop by this. The MSI range
+    /// for Standby is independent of `enable_msi`.
+    pub fn set_low_power_clock_plan(mut self, plan: LowPowerClockPlan) -> Self {
+        self.low_power_plan = plan;
+        self
+    }
+
+    /// Switches the clocks of the ADCs and DACs off in Sleep mode as selected by `gating`
+    ///
+    /// Applied by `freeze`, even if the current clocks are assumed. Without a call to this method,
+    /// `freeze` leaves the Sleep mode enables of the ADCs and DACs as they are.
+    pub fn gate_analog_in_sleep(mut self, gating: AnalogSleepGating) -> Self {
+        self.analog_sleep_gating = Some(gating);
+        self
     }
 
     /// Sets LSI clock on (the default) or off
@@ -84,12 +220,68 @@ impl CFGR {
     /// Sets a frequency for the APB1 bus
     pub fn set_pclk1_freq(mut self, freq: Hertz) -> Self {
         self.pclk1 = Some(Pclk1Config::new(freq));
+        self.timclk1 = None;
         self
     }
 
     /// Sets a frequency for the APB2 bus
     pub fn set_pclk2_freq(mut self, freq: Hertz) -> Self {
         self.pclk2 = Some(Pclk2Config::new(freq));
+        self.timclk2 = None;
+        self
+    }
+
+    /// Sets the frequency of the timers on the APB1 bus and derives PCLK1 from it
+    ///
+    /// The timers run at twice PCLK1, unless PCLK1 equals HCLK. This replaces `set_pclk1_freq`.
+    pub fn set_timclk1_freq(mut self, freq: Hertz) -> Self {
+        self.timclk1 = Some(freq);
+        self.pclk1 = None;
+        self
+    }
+
+    /// Sets the frequency of the timers on the APB2 bus and derives PCLK2 from it
+    ///
+    /// The timers run at twice PCLK2, unless PCLK2 equals HCLK. This replaces `set_pclk2_freq`.
+    pub fn set_timclk2_freq(mut self, freq: Hertz) -> Self {
+        self.timclk2 = Some(freq);
+        self.pclk2 = None;
+        self
+    }
+
+    /// Like `set_pclk1_freq`, but fails right away if HCLK is already set and PCLK1 can't be
+    /// derived from it, instead of panicking in `freeze`
+    pub fn try_set_pclk1_freq(self, freq: Hertz) -> Result<Self, ClockError> {
+        if let Some(hclk) = self.hclk {
+            Prescaler::try_from_ratio(hclk.freq(), freq).ok_or(ClockError::InvalidPclk1Divider)?;
+        }
+
+        Ok(self.set_pclk1_freq(freq))
+    }
+
+    /// Like `set_pclk2_freq`, but fails right away if HCLK is already set and PCLK2 can't be
+    /// derived from it, instead of panicking in `freeze`
+    pub fn try_set_pclk2_freq(self, freq: Hertz) -> Result<Self, ClockError> {
+        if let Some(hclk) = self.hclk {
+            Prescaler::try_from_ratio(hclk.freq(), freq).ok_or(ClockError::InvalidPclk2Divider)?;
+        }
+
+        Ok(self.set_pclk2_freq(freq))
+    }
+
+    /// Requires the APB1 prescaler to be at least `min`, see `Pclk1Config::with_min_prescaler`
+    ///
+    /// `freeze` panics if the PCLK1 frequency would need a smaller prescaler.
+    pub fn set_pclk1_min_prescaler(mut self, min: Prescaler) -> Self {
+        self.pclk1_min_prescaler = min;
+        self
+    }
+
+    /// Requires the APB2 prescaler to be at least `min`, see `Pclk2Config::with_min_prescaler`
+    ///
+    /// `freeze` panics if the PCLK2 frequency would need a smaller prescaler.
+    pub fn set_pclk2_min_prescaler(mut self, min: Prescaler) -> Self {
+        self.pclk2_min_prescaler = min;
         self
     }
 
@@ -102,6 +294,29 @@ impl CFGR {
         self
     }
 
+    /// Selects the SYSCLK source if no explicit `set_sysclk` is given
+    ///
+    /// SYSCLK then runs at the frequency of `source`, which has to be enabled. Without a
+    /// preference the MSI is used, even if other clocks such as the HSI16 are enabled.
+    pub fn prefer_sysclk_source(mut self, source: SysclkSource) -> Self {
+        self.sysclk_preference = Some(source);
+        self
+    }
+
+    /// Sets the PLL input divider (`RCC_PLLCFGR.PLLM`), which is shared by the PLLs
+    ///
+    /// Except on the L4+ parts, the main PLL and PLLSAI1 both divide their source by it, so their
+    /// `in_div` has to match, which `freeze` checks. On the L4+ parts only the main PLL uses it,
+    /// PLLSAI1 and PLLSAI2 have their own dividers. Without this setting the divider is taken from
+    /// the main PLL or, if that is off, from PLLSAI1.
+    pub fn set_pll_input_divider(mut self, div: u8) -> Self {
+        assert!(div >= 1);
+        assert!(div <= 8);
+
+        self.pll_input_divider = Some(div);
+        self
+    }
+
     /// Sets the PLL source
     pub fn enable_pll(
         mut self,
@@ -122,129 +337,1127 @@ impl CFGR {
         self
     }
 
+    /// Runs the PLL only for its Q output, e.g. as 48 MHz clock with SYSCLK on the MSI
+    ///
+    /// The R output stays disabled, so SYSCLK can't be taken from the PLL. See
+    /// `PllConfig::q_only`.
+    pub fn enable_pll_q_only(
+        mut self,
+        source: PllSource,
+        in_div: u8,
+        out_mul: u8,
+        q_div: PllOutputDivider,
+    ) -> Self {
+        self.pll = Some(PllConfig::q_only(source, in_div, out_mul, q_div));
+        self
+    }
+
+    /// Preset for SYSCLK at 80 MHz from the PLL on the HSI16, which needs no external components
+    ///
+    /// The PLL runs at HSI16 / 1 * 10 / 2. HCLK, PCLK1 and PCLK2 run at the full 80 MHz. Later
+    /// builder calls can still adjust the configuration.
+    pub fn hsi16_80mhz(self) -> Self {
+        self.enable_hsi16(true)
+            .enable_pll(
+                PllSource::HSI16,
+                Hertz::MHz(80),
+                1,
+                10,
+                PllOutputDivider::Div2,
+            )
+            .set_sysclk(SysclkSource::PLL, Hertz::MHz(80))
+            .set_hclk_freq(Hertz::MHz(80))
+            .set_pclk1_freq(Hertz::MHz(80))
+            .set_pclk2_freq(Hertz::MHz(80))
+    }
+
+    /// Preset for SYSCLK at 80 MHz from the PLL and the 48 MHz clock for USB, RNG and SDMMC from
+    /// PLLSAI1
+    ///
+    /// The main PLL can't provide both, as no VCO frequency divides into 80 and 48 MHz with the
+    /// available output dividers. Both PLLs share the input divider, which is chosen so that both
+    /// outputs are exact. The HSE or MSI have to be enabled before if they are the source. HCLK,
+    /// PCLK1 and PCLK2 run at the full 80 MHz.
+    ///
+    /// Fails if `source` is not enabled or no input divider derives exactly 80 and 48 MHz from it.
+    #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+    pub fn preset_usb_80mhz(self, source: PllSource) -> Result<Self, ClockError> {
+        let source_freq = self.pll_source_freq(source)?.raw();
+        let (in_div, (pll_mul, pll_div), (sai_mul, sai_div)) = (1..=8u8)
+            .filter(|&in_div| source_freq % in_div as u32 == 0)
+            .map(|in_div| (in_div, source_freq / in_div as u32))
+            .filter(|(_, vco_input)| (4_000_000..=16_000_000).contains(vco_input))
+            .find_map(|(in_div, vco_input)| {
+                Some((
+                    in_div,
+                    pll::exact_multiplier(vco_input, Hertz::MHz(80))?,
+                    pll::exact_multiplier(vco_input, Hertz::MHz(48))?,
+                ))
+            })
+            .ok_or(ClockError::PllFreqMismatch)?;
+
+        Ok(self
+            .enable_pll(source, Hertz::MHz(80), in_div, pll_mul, pll_div)
+            .enable_pllsai1(PllSai1Config::new(source, in_div, sai_mul).q_output(sai_div))
+            .set_clk48_source(Clk48ClockSource::PLLSAI1Q)
+            .set_sysclk(SysclkSource::PLL, Hertz::MHz(80))
+            .set_hclk_freq(Hertz::MHz(80))
+            .set_pclk1_freq(Hertz::MHz(80))
+            .set_pclk2_freq(Hertz::MHz(80)))
+    }
+
+    /// Preset for SYSCLK straight from the HSE, without the PLL, e.g. for a low-jitter external
+    /// clock
+    ///
+    /// The HSE has to be enabled before. SYSCLK, HCLK, PCLK1 and PCLK2 all run at the HSE
+    /// frequency, which at up to 48 MHz is within the limits of the high-performance voltage
+    /// range. `freeze` checks it against the limit of the low-power range, 26 MHz, if that range
+    /// is active.
+    #[track_caller]
+    pub fn sysclk_from_hse_direct(self) -> Self {
+        let hse = self
+            .hse
+            .as_ref()
+            .expect("The HSE has to be enabled before the preset");
+        hse.validate().expect("Invalid HSE configuration");
+        let freq = hse.speed();
+
+        self.set_sysclk(SysclkSource::HSE, freq)
+            .set_hclk_freq(freq)
+            .set_pclk1_freq(freq)
+            .set_pclk2_freq(freq)
+    }
+
+    /// Preset for SYSCLK and the 48 MHz clock for USB, RNG and SDMMC both from the MSI at 48 MHz,
+    /// without any PLL
+    ///
+    /// A free running MSI is not accurate enough for USB, so the LSE has to be enabled as well,
+    /// which `freeze` checks. It then calibrates the MSI continuously (`RCC_CR.MSIPLLEN`). HCLK,
+    /// PCLK1 and PCLK2 run at the full 48 MHz.
+    pub fn preset_usb_msi_48mhz(mut self) -> Self {
+        self.usb_on_msi = true;
+        self.enable_msi(MsiFreq::RANGE48M)
+            .set_clk48_source(Clk48ClockSource::MSI)
+            .set_sysclk(SysclkSource::MSI, Hertz::MHz(48))
+            .set_hclk_freq(Hertz::MHz(48))
+            .set_pclk1_freq(Hertz::MHz(48))
+            .set_pclk2_freq(Hertz::MHz(48))
+    }
+
+    /// Enables PLLSAI1, e.g. to provide the 48 MHz clock
+    #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+    pub fn enable_pllsai1(mut self, config: PllSai1Config) -> Self {
+        self.pllsai1 = Some(config);
+        self
+    }
+
+    /// Selects the 48 MHz clock for USB, RNG and SDMMC (`RCC_CCIPR.CLK48SEL`)
+    ///
+    /// `freeze` checks that the selected clock is configured to run at exactly 48 MHz. For
+    /// `PLLSAI1Q` this is the Q output of the configuration passed to `enable_pllsai1`.
+    pub fn set_clk48_source(mut self, source: Clk48ClockSource) -> Self {
+        self.clk48_source = Some(source);
+        self
+    }
+
+    /// Selects the kernel clock of the LPUART1 (`RCC_CCIPR.LPUART1SEL`)
+    ///
+    /// With the LSE the LPUART1 can receive in Stop mode, at up to 9600 baud, as the kernel clock
+    /// has to be at least three times the baud rate. `freeze` checks that the selected clock is
+    /// enabled and enables the HSI16 if it is selected.
+    pub fn set_lpuart1_source(mut self, source: UsartClockSource) -> Self {
+        self.lpuart1_source = Some(source);
+        self
+    }
+
+    /// Selects the kernel clock of the SAI1 (`RCC_CCIPR.SAI1SEL`, `RCC_CCIPR2.SAI1SEL` on the
+    /// L4R/L4S parts)
+    ///
+    /// The SAI1 kernel clock is also the audio clock of the DFSDM1, see
+    /// `set_dfsdm1_audio_source`.
+    pub fn set_sai1_source(mut self, source: SaiClockSource) -> Self {
+        self.sai1_source = Some(source);
+        self
+    }
+
+    /// Selects the audio clock of the DFSDM1 (`RCC_CCIPR2.ADFSDMSEL`)
+    ///
+    /// Selecting the SAI1 clock requires its source to be set with `set_sai1_source`, `freeze`
+    /// writes that selection first.
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    pub fn set_dfsdm1_audio_source(mut self, source: DfsdmAudioClockSource) -> Self {
+        self.dfsdm1_audio_source = Some(source);
+        self
+    }
+
+    /// Records the frequency of the clock on the SAI_EXTCLK pin
+    ///
+    /// The HAL can't measure it, but with it `Clocks::sai1_clk` and `Clocks::sai2_clk` return a
+    /// frequency when the SAIs are clocked from the pin.
+    pub fn set_sai_ext_clk_freq(mut self, freq: Hertz) -> Self {
+        self.sai_ext_clk = Some(freq);
+        self
+    }
+
+    /// Routes `source` divided by `prescaler` to the MCO pin
+    ///
+    /// `freeze` checks that the source is enabled and keeps the MSI and HSI16 running while they
+    /// are selected. The pin itself has to be set up as alternate function with a speed that
+    /// suits the output frequency, see `Clocks::mco_pin_speed`.
+    pub fn enable_mco(mut self, source: McoSource, prescaler: McoPrescaler) -> Self {
+        self.mco = Some((source, prescaler));
+        self
+    }
+
+    /// Selects the kernel clock of the USART1 with the lowest error for `baud`
+    ///
+    /// PCLK2, SYSCLK and the HSI16 are compared with `usart_baud_error`, preferring them in that
+    /// order on equal error, so the HSI16 is only enabled if it is more accurate. The choice is
+    /// available from `Clocks::usart1_auto_source`.
+    pub fn auto_usart1_source(mut self, baud: u32) -> Self {
+        self.usart1_target_baud = Some(baud);
+        self
+    }
+
+    /// Enables PLLSAI2 to provide the DSI and LTDC clocks
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    pub fn enable_pllsai2(mut self, config: PllSai2Config) -> Self {
+        self.pllsai2 = Some(config);
+        self
+    }
+
+    /// Sets the PLL up on the MSI, which has to run at `msi_range`
+    ///
+    /// Unlike `enable_pll` with `PllSource::MSI`, `freeze` rejects the configuration if the MSI is
+    /// enabled with a different range than the PLL parameters have been calculated for.
+    pub fn enable_pll_with_msi(
+        mut self,
+        msi_range: MsiFreq,
+        target_freq: Hertz,
+        in_div: u8,
+        out_mul: u8,
+        out_div: PllOutputDivider,
+    ) -> Self {
+        self.pll = Some(
+            PllConfig::new(PllSource::MSI, target_freq, in_div, out_mul, out_div)
+                .with_msi_range(msi_range),
+        );
+        self
+    }
+
+    /// Sets the PLL up to derive `target_freq` from `source` running at `source_freq`, with the
+    /// parameters found by `PllConfig::autosetting`
+    ///
+    /// # Panics
+    ///
+    /// Panics if no PLL configuration hits `target_freq` exactly.
+    #[track_caller]
     pub fn enable_pll_autosetting(
-        self,
-        _source: PllSource,
-        _source_freq: Hertz,
-        _target_freq: Hertz,
+        mut self,
+        source: PllSource,
+        source_freq: Hertz,
+        target_freq: Hertz,
     ) -> Self {
-        todo!()
+        self.pll = Some(
+            PllConfig::autosetting(source, source_freq, target_freq)
+                .expect("No PLL configuration reaches the target frequency"),
+        );
+        self
+    }
+
+    /// Runs SYSCLK at `mhz` MHz from the PLL on the HSI16, which needs no external components
+    ///
+    /// The PLL parameters are found by `enable_pll_autosetting`. HCLK runs at SYSCLK and so do
+    /// PCLK1 and PCLK2, unless set otherwise, which is fine as the APB limit equals the SYSCLK
+    /// limit of 80 MHz.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no PLL configuration reaches `mhz` MHz from the HSI16.
+    #[track_caller]
+    pub fn sysclk_mhz(self, mhz: u32) -> Self {
+        let freq = Hertz::MHz(mhz);
+
+        self.enable_hsi16(true)
+            .enable_pll_autosetting(PllSource::HSI16, HSI16_FREQ, freq)
+            .set_sysclk(SysclkSource::PLL, freq)
+            .set_hclk_freq(freq)
+    }
+
+    /// Reuses the clock configuration that is already active, e.g. when a bootloader or debugger
+    /// has set up the clocks before.
+    ///
+    /// On `freeze` the live RCC state is compared to this configuration. If it matches, the clocks
+    /// are left untouched. Otherwise they are reset and configured as usual.
+    pub fn assume_current(mut self) -> Self {
+        self.assume_current = true;
+        self
+    }
+
+    /// Skips the drop to the MSI at 4 MHz at the start of `freeze`, for a faster reconfiguration
+    ///
+    /// SYSCLK then switches directly from its current source to the new one. The drop still
+    /// happens if the current SYSCLK source, or the source of the PLL driving it, is reprogrammed
+    /// by `freeze`. The flash latency covers both the old and new HCLK until the switch is done.
+    /// The LSE timeout assumes SYSCLK runs on the MSI and is inaccurate otherwise.
+    pub fn without_reset(mut self) -> Self {
+        self.skip_reset = true;
+        self
+    }
+
+    /// Ramps HCLK up to its target in `steps` halving steps instead of a single jump, to limit
+    /// the load step on the power supply
+    ///
+    /// SYSCLK is switched with the AHB prescaler set to 2^`steps` times its final value, which is
+    /// then halved about every microsecond. Prescalers the hardware doesn't provide (32) are
+    /// skipped. The flash latency is set for the final HCLK before the first step, which also
+    /// covers every slower intermediate step.
+    pub fn set_hclk_ramp(mut self, steps: u8) -> Self {
+        assert!(steps <= 9);
+
+        self.hclk_ramp_steps = steps;
+        self
+    }
+
+    /// Calls `hook` repeatedly while `freeze` waits for a clock to become ready
+    ///
+    /// By default the core busy spins. Especially the LSE may take a long time to start up.
+    pub fn with_wait_hook(mut self, hook: WaitHook) -> Self {
+        self.wait_hook = Some(hook);
+        self
+    }
+
+    /// Returns true if `freeze` writes to the backup domain and therefore sets `PWR_CR1.DBP`
+    ///
+    /// This is the case when the LSE is configured. The RTC clock source and the LSCO output are
+    /// not set up by `CFGR` and need their own backup domain access.
+    pub fn requires_backup_domain_access(&self) -> bool {
+        self.lse.is_some()
+    }
+
+    /// Returns a worst-case estimate of the time `freeze` spends waiting for clocks to start
+    ///
+    /// This sums the datasheet startup times of the enabled oscillators and the lock times of
+    /// the enabled PLLs, which `freeze` waits for one after the other. The LSE crystal dominates
+    /// with up to 2 s, capped by `set_lse_timeout`. Use it to budget watchdog timeouts around
+    /// `freeze`. Oscillators in bypass mode are counted as ready immediately.
+    pub fn estimated_startup_time(&self) -> MicroSeconds {
+        const LSE_STARTUP_US: u32 = 2_000_000;
+        const HSE_STARTUP_US: u32 = 2_000;
+        const LSI_STARTUP_US: u32 = 130;
+        const PLL_LOCK_US: u32 = 40;
+        const MSI_STARTUP_US: u32 = 6;
+        const HSI48_STARTUP_US: u32 = 6;
+        const HSI16_STARTUP_US: u32 = 2;
+
+        let mut us = 0;
+        if let Some(lse) = &self.lse {
+            if lse.bypass == CrystalBypass::Disable {
+                us += match self.lse_timeout {
+                    // Capped in milliseconds, as long timeouts don't fit a u32 in microseconds
+                    Some(timeout) => {
+                        timeout.ticks().min(LSE_STARTUP_US / 1000) * 1000 + LSI_STARTUP_US
+                    }
+                    None => LSE_STARTUP_US,
+                };
+            }
+        }
+        if matches!(&self.hse, Some(hse) if hse.bypass() == CrystalBypass::Disable) {
+            us += HSE_STARTUP_US;
+        }
+        if self.lsi_on {
+            us += LSI_STARTUP_US;
+        }
+        if self.msi.is_some() {
+            us += MSI_STARTUP_US;
+        }
+        if self.hsi48_on {
+            us += HSI48_STARTUP_US;
+        }
+        if self.hsi16_required() {
+            us += HSI16_STARTUP_US;
+        }
+        if self.pll.is_some() {
+            us += PLL_LOCK_US;
+        }
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        if self.pllsai1.is_some() {
+            us += PLL_LOCK_US;
+        }
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        if self.pllsai2.is_some() {
+            us += PLL_LOCK_US;
+        }
+
+        MicroSeconds::micros(us)
+    }
+
+    /// Checks the configuration for consistency without touching the hardware
+    ///
+    /// Besides the frequency constraints this covers the dependencies between the clocks: the
+    /// SYSCLK source and the PLL sources have to be enabled and the LSE clock security system
+    /// needs the LSI.
+    ///
+    /// `freeze` runs this, as part of `validate_for`, before writing any register and panics on an
+    /// error, so an invalid configuration never leaves the clocks partially set up.
+    pub fn validate(&self) -> Result<(), ClockError> {
+        if let Some(hse) = &self.hse {
+            hse.validate()?;
+        }
+        if let Some(lse) = &self.lse {
+            if lse.css != ClockSecuritySystem::Disable && !self.lsi_on {
+                return Err(ClockError::LseCssWithoutLsi);
+            }
+        }
+
+        if let Some(pllm) = self.shared_pll_input_divider() {
+            if matches!(&self.pll, Some(pll) if pll.in_div() != pllm) {
+                return Err(ClockError::PllInputDividerConflict);
+            }
+            #[cfg(not(any(
+                feature = "stm32l412",
+                feature = "stm32l422",
+                feature = "stm32l4r9",
+                feature = "stm32l4s9",
+            )))]
+            if matches!(&self.pllsai1, Some(pllsai1) if pllsai1.in_div() != pllm) {
+                return Err(ClockError::PllInputDividerConflict);
+            }
+        }
+
+        if let Some(pll) = &self.pll {
+            if pll.msi_range().is_some() && pll.msi_range() != self.msi {
+                return Err(ClockError::PllMsiRangeMismatch);
+            }
+            pll.validate(self.pll_source_freq(pll.source())?)?;
+        }
+
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        if let Some(pllsai2) = &self.pllsai2 {
+            if matches!(&self.pll, Some(pll) if pll.source() != pllsai2.source()) {
+                return Err(ClockError::PllSourceConflict);
+            }
+            pllsai2.validate(self.pll_source_freq(pllsai2.source())?)?;
+        }
+
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        if let Some(pllsai1) = &self.pllsai1 {
+            if matches!(&self.pll, Some(pll) if pll.source() != pllsai1.source()) {
+                return Err(ClockError::PllSourceConflict);
+            }
+            pllsai1.validate(self.pll_source_freq(pllsai1.source())?)?;
+        }
+
+        if let Some(source) = self.clk48_source {
+            match self.clk48_freq(source) {
+                None => return Err(ClockError::Clk48SourceDisabled(source)),
+                Some(freq) if freq != Hertz::MHz(48) => return Err(ClockError::Clk48FreqMismatch),
+                Some(_) => {}
+            }
+            if source == Clk48ClockSource::MSI && self.usb_on_msi && self.lse.is_none() {
+                return Err(ClockError::Clk48MsiWithoutLse);
+            }
+        }
+        if self.lpuart1_source == Some(UsartClockSource::LSE) && self.lse.is_none() {
+            return Err(ClockError::Lpuart1SourceDisabled);
+        }
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        if let Some(source) = self.dfsdm1_audio_source {
+            let enabled = match source {
+                DfsdmAudioClockSource::SAI1 => self.sai1_source.is_some(),
+                DfsdmAudioClockSource::HSI16 => true,
+                DfsdmAudioClockSource::MSI => self.msi.is_some(),
+            };
+            if !enabled {
+                return Err(ClockError::DfsdmAudioSourceDisabled(source));
+            }
+        }
+        if let Some((source, _)) = self.mco {
+            let enabled = match source {
+                McoSource::SYSCLK | McoSource::HSI16 => true,
+                McoSource::MSI => self.msi.is_some(),
+                McoSource::HSE => self.hse.is_some(),
+                McoSource::PLL => matches!(&self.pll, Some(pll) if pll.r_freq().is_some()),
+                McoSource::LSI => self.lsi_on,
+                McoSource::LSE => self.lse.is_some(),
+            };
+            if !enabled {
+                return Err(ClockError::McoSourceDisabled(source));
+            }
+        }
+
+        if self.sysclk.is_none() {
+            if let Some(source) = self.sysclk_preference {
+                self.sysclk_source_freq(source)?;
+            } else if self.msi.is_none() {
+                return Err(ClockError::NoSysclk);
+            }
+        }
+        let sysclk = self.create_sysclk_config();
+        if self.sysclk_source_freq(sysclk.source_clock)? != sysclk.speed {
+            return Err(ClockError::SysclkFreqMismatch);
+        }
+
+        if sysclk.speed > MAX_SYSCLK_RANGE1 {
+            return Err(ClockError::SysclkTooHigh);
+        }
+
+        let hclk = self.create_hclk_config(&sysclk).freq();
+        if hclk > MAX_HCLK {
+            return Err(ClockError::HclkTooHigh);
+        }
+        if HclkDivider::is_div32(sysclk.speed, hclk) {
+            return Err(ClockError::HclkDivider32);
+        }
+        HclkDivider::try_from_ratio(sysclk.speed, hclk).ok_or(ClockError::InvalidHclkDivider)?;
+        if let Some(timclk1) = self.timclk1 {
+            Prescaler::pclk_for_timclk(hclk, timclk1).ok_or(ClockError::InvalidTimclk1Freq)?;
+        }
+        if let Some(timclk2) = self.timclk2 {
+            Prescaler::pclk_for_timclk(hclk, timclk2).ok_or(ClockError::InvalidTimclk2Freq)?;
+        }
+
+        let pclk1 = self.pclk1_config(hclk);
+        if pclk1.freq() > MAX_PCLK1 {
+            return Err(ClockError::Pclk1TooHigh);
+        }
+        let ppre1 =
+            Prescaler::try_from_ratio(hclk, pclk1.freq()).ok_or(ClockError::InvalidPclk1Divider)?;
+        if ppre1.div_factor() < pclk1.min_prescaler().div_factor() {
+            return Err(ClockError::Pclk1PrescalerBelowMinimum);
+        }
+        let pclk2 = self.pclk2_config(hclk);
+        if pclk2.freq() > MAX_PCLK2 {
+            return Err(ClockError::Pclk2TooHigh);
+        }
+        let ppre2 =
+            Prescaler::try_from_ratio(hclk, pclk2.freq()).ok_or(ClockError::InvalidPclk2Divider)?;
+        if ppre2.div_factor() < pclk2.min_prescaler().div_factor() {
+            return Err(ClockError::Pclk2PrescalerBelowMinimum);
+        }
+
+        if self.usart1_target_baud.is_some() && self.usart1_auto_choice().is_none() {
+            return Err(ClockError::NoUsart1SourceForBaud);
+        }
+
+        Ok(())
     }
 
+    /// Like `validate`, but also checks SYSCLK against the limit of the voltage range `pwr` is
+    /// currently set to
+    pub fn validate_for(&self, pwr: &mut Pwr) -> Result<(), ClockError> {
+        self.validate()?;
+
+        if self.create_sysclk_config().speed > pwr.max_sysclk() {
+            return Err(ClockError::SysclkAboveVoltageRange);
+        }
+
+        Ok(())
+    }
+
+    // Frequency of the clock feeding the PLL, if it is enabled
+    fn pll_source_freq(&self, source: PllSource) -> Result<Hertz, ClockError> {
+        let freq = match source {
+            PllSource::HSE => self.hse.as_ref().map(|hse| hse.speed()),
+            PllSource::HSI16 => Some(HSI16_FREQ),
+            PllSource::MSI => self.msi.map(|msi| msi.to_hertz()),
+        };
+
+        freq.ok_or(ClockError::PllSourceDisabled(source))
+    }
+
+    // Frequency of the clock selected as 48 MHz clock, if it is enabled
+    fn clk48_freq(&self, source: Clk48ClockSource) -> Option<Hertz> {
+        match source {
+            #[cfg(not(any(
+                feature = "stm32l471",
+                feature = "stm32l475",
+                feature = "stm32l476",
+                feature = "stm32l486"
+            )))]
+            Clk48ClockSource::HSI48 => self.hsi48_on.then_some(Hertz::MHz(48)),
+            Clk48ClockSource::MSI => self.msi.map(|msi| msi.to_hertz()),
+            Clk48ClockSource::PLLQ => {
+                let pll = self.pll.as_ref()?;
+                pll.q_freq(self.pll_source_freq(pll.source()).ok()?)
+            }
+            #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+            Clk48ClockSource::PLLSAI1Q => {
+                let pllsai1 = self.pllsai1.as_ref()?;
+                pllsai1.q_freq(self.pll_source_freq(pllsai1.source()).ok()?)
+            }
+        }
+    }
+
+    // Frequency of the clock feeding SYSCLK, if it is enabled
+    fn sysclk_source_freq(&self, source: SysclkSource) -> Result<Hertz, ClockError> {
+        let freq = match source {
+            SysclkSource::HSE => self.hse.as_ref().map(|hse| hse.speed()),
+            SysclkSource::HSI16 => self.hsi16_on.then_some(HSI16_FREQ),
+            SysclkSource::MSI => self.msi.map(|msi| msi.to_hertz()),
+            SysclkSource::PLL => self.pll.as_ref().and_then(|pll| pll.r_freq()),
+        };
+
+        freq.ok_or(ClockError::SysclkSourceDisabled(source))
+    }
+
+    /// Applies the configuration and returns the resulting clocks
+    ///
+    /// # Panics
+    ///
+    /// Panics if `validate_for` fails, before any clock is touched. All configuration checks
+    /// live there, the oscillators, PLLs and prescalers are then started without checking them
+    /// again.
+    ///
+    /// The MSI and HSI16 are switched off if this configuration doesn't use them, regardless of
+    /// who enabled them, see `keep_msi_running` and `enable_hsi16`.
+    #[track_caller]
     pub fn freeze(self, acr: &mut ACR, pwr: &mut Pwr) -> Clocks {
+        // `PwrExt::constrain` enables the PWR clock, but it may have been gated since. PWR_CR1 reads
+        // as zero and writes to it (backup domain access, voltage scaling) are ignored while it is
+        // off.
+        if PWR::is_disabled() {
+            // NOTE(unsafe) only sets PWREN, which nothing else in the HAL relies on being clear
+            unsafe { PWR::enable_unchecked() };
+        }
+
+        self.validate_for(pwr).expect("Invalid clock configuration");
+
+        let range = pwr.power_range();
         let rcc = unsafe { &*RCC::ptr() };
+        self.apply(rcc, acr.acr(), pwr.cr1.reg(), range)
+    }
 
-        reset_clocks(rcc);
-        let mut clocks = Clocks::default();
+    // The part of `freeze` after the validation, on the given registers, with the regulator in
+    // the voltage range `range`
+    fn apply(
+        self,
+        rcc: &RegisterBlock,
+        acr: &flash::ACR,
+        pwr_cr1: &pwr::CR1,
+        range: VosRange,
+    ) -> Clocks {
+        // Independent of the clock tree, so also applied to the clocks assumed to be current
+        if let Some(gating) = self.analog_sleep_gating {
+            gating.freeze(rcc);
+        }
+
+        if self.assume_current {
+            if let Some(clocks) = self.current_clocks(rcc, acr, range) {
+                return clocks;
+            }
+        }
+
+        if !self.skip_reset || self.reprograms_sysclk_source(rcc) {
+            reset_clocks(rcc, self.wait_hook);
+        }
+
+        let mut clocks = Clocks {
+            sai_ext_clk: self.sai_ext_clk,
+            ..Clocks::default()
+        };
         self.setup_lsi(rcc, &mut clocks);
-        self.setup_lse(rcc, pwr, &mut clocks);
+        self.setup_lse(rcc, pwr_cr1, &mut clocks);
         self.setup_hse(rcc, &mut clocks);
+        #[cfg(not(any(
+            feature = "stm32l471",
+            feature = "stm32l475",
+            feature = "stm32l476",
+            feature = "stm32l486"
+        )))]
         self.setup_hsi48(rcc, &mut clocks);
         self.setup_hsi16(rcc, &mut clocks);
+        self.setup_pll_input(rcc);
         self.setup_pll(rcc, &mut clocks);
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        self.setup_pllsai1(rcc, &mut clocks);
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        self.setup_pllsai2(rcc, &mut clocks);
+        self.setup_clk48(rcc);
+        self.setup_lpuart1(rcc);
+        self.setup_sai1(rcc);
+        self.setup_usart1(rcc, &mut clocks);
 
         let sysclk = self.create_sysclk_config();
         let hclk = self.create_hclk_config(&sysclk);
 
         self.setup_periph_clocks(rcc, &hclk, &mut clocks);
-        self.adjust_flash_wait_states(acr, &hclk);
+        self.set_flash_wait_states(rcc, acr, &hclk, range, &mut clocks);
 
         self.configure_msi(rcc, &mut clocks);
 
+        self.ramp_hclk(rcc, &hclk, &sysclk, true);
         self.setup_sysclk(&sysclk, rcc, &mut clocks);
+        self.ramp_hclk(rcc, &hclk, &sysclk, false);
         self.setup_hclk(rcc, &hclk, &sysclk, &mut clocks);
+        self.set_flash_wait_states(rcc, acr, &hclk, range, &mut clocks);
 
-        self.clean_msi(rcc);
+        self.clean_msi(rcc, &sysclk, &mut clocks);
+        self.low_power_plan.freeze(rcc);
+        clocks.low_power_plan = LowPowerClockPlan::from_hardware(rcc);
+        self.clean_hsi16(rcc);
+        self.setup_mco(rcc, &mut clocks);
 
         clocks
     }
 
+    // Returns the clocks currently running in hardware, if they match the requested configuration.
+    fn current_clocks(
+        &self,
+        rcc: &RegisterBlock,
+        acr: &flash::ACR,
+        range: VosRange,
+    ) -> Option<Clocks> {
+        let clocks = Clocks::from_hardware(rcc, acr, self.hse.as_ref().map(|hse| hse.speed()))?;
+
+        let sysclk = self.create_sysclk_config();
+        let hclk = self.create_hclk_config(&sysclk).freq();
+        let pclk1 = self.pclk1_config(hclk).freq();
+        let pclk2 = self.pclk2_config(hclk).freq();
+
+        let matches = clocks.sysclk_source == sysclk.source_clock
+            && clocks.sysclk == sysclk.speed
+            && clocks.hclk == hclk
+            && clocks.pclk1 == pclk1
+            && clocks.pclk2 == pclk2
+            && clocks.low_power_plan == self.low_power_plan
+            && clocks.hsi16_in_stop == self.hsi16_in_stop
+            && clocks.mco
+                == self.mco.and_then(|(source, prescaler)| {
+                    clocks.mco_from_bits(source as u8, prescaler as u8)
+                })
+            && rcc.cr.read().hsiasfs().bit() == self.hsi16_wakeup_start
+            && (!self.msi_required(&sysclk) || clocks.msi == self.msi)
+            && (self.hse.is_none() || clocks.hse.is_some())
+            && (self.pll.is_none() || clocks.pll == self.pll.as_ref().and_then(|pll| pll.r_freq()))
+            && (!self.hsi16_required() || clocks.hsi16)
+            && (!self.hsi48_on || clocks.hsi48)
+            && (!self.lsi_on || clocks.lsi)
+            && (self.lse.is_none() || clocks.lse)
+            && acr.read().latency().bits() >= flash_latency_bits(hclk, range);
+
+        if matches {
+            Some(Clocks {
+                sai_ext_clk: self.sai_ext_clk,
+                ..clocks
+            })
+        } else {
+            None
+        }
+    }
+
     fn setup_lsi(&self, rcc: &RegisterBlock, clocks: &mut Clocks) {
         if !self.lsi_on {
             return;
         }
 
         rcc.csr.modify(|_, w| w.lsion().set_bit());
-        while rcc.csr.read().lsirdy().bit_is_clear() {}
+        wait_until(self.wait_hook, || rcc.csr.read().lsirdy().bit_is_set());
 
         clocks.lsi = true;
     }
 
-    fn setup_lse(&self, rcc: &RegisterBlock, pwr: &mut Pwr, clocks: &mut Clocks) {
+    fn setup_lse(&self, rcc: &RegisterBlock, pwr_cr1: &pwr::CR1, clocks: &mut Clocks) {
         if let Some(lse_cfg) = &self.lse {
             // Unlocke the backup domain
-            pwr.cr1.reg().modify(|_, w| w.dbp().set_bit());
-
-            rcc.bdcr.modify(|_, w| {
-                // Enable the LSE
-                w.lseon().set_bit();
+            pwr_cr1.modify(|_, w| w.dbp().set_bit());
 
-                // Set drive strength if we use a crystal, if a complete oscillator is used, set the LSE bypass bit
-                match lse_cfg.bypass {
-                    CrystalBypass::Enable => w.lsebyp().set_bit(),
-                    CrystalBypass::Disable => unsafe { w.lsedrv().bits(0b11) },
-                };
+            // The backup domain is not affected by `reset_clocks`. An LSE that is already running,
+            // e.g. from a previous `freeze`, may be clocking the RTC and is left untouched. LSEBYP
+            // can't be changed while the LSE is enabled anyway.
+            if rcc.bdcr.read().lserdy().bit_is_clear() {
+                rcc.bdcr.modify(|_, w| {
+                    // Enable the LSE
+                    w.lseon().set_bit();
 
-                w
-            });
+                    // Set drive strength if we use a crystal, if a complete oscillator is used, set the LSE bypass bit
+                    match lse_cfg.bypass {
+                        CrystalBypass::Enable => w.lsebyp().set_bit(),
+                        CrystalBypass::Disable => unsafe { w.lsedrv().bits(0b11) },
+                    };
 
-            // Wait until LSE is running
-            while rcc.bdcr.read().lserdy().bit_is_clear() {}
+                    w
+                });
 
-            // Make sure to have a backup clock signal if the clock security system is enabled and we have to fall back
-            if lse_cfg.css == ClockSecuritySystem::Enable {
-                if !self.lsi_on {
-                    panic!("The clock security system of the LSE uses LSI as a fallback. LSI must be enabled too");
+                // Wait until LSE is running
+                let ready = || rcc.bdcr.read().lserdy().bit_is_set();
+                match self.lse_timeout {
+                    Some(timeout) => {
+                        // SYSCLK runs on the MSI after `reset_clocks`
+                        let sysclk = MsiFreq::from_hardware(rcc).to_hertz();
+                        if !wait_until_timeout(self.wait_hook, timeout, sysclk, ready) {
+                            self.fall_back_to_lsi(rcc, clocks);
+                            return;
+                        }
+                    }
+                    None => wait_until(self.wait_hook, ready),
                 }
+            }
 
-                // Enable CSS and interrupt
+            // The LSI as backup clock for the clock security system has been checked in `validate`
+            if lse_cfg.css != ClockSecuritySystem::Disable {
+                // Enable CSS and, unless the failure is polled for, the interrupt
                 rcc.bdcr.modify(|_, w| w.lsecsson().set_bit());
-                rcc.cier.modify(|_, w| w.lsecssie().set_bit());
+                rcc.cier
+                    .modify(|_, w| w.lsecssie().bit(lse_cfg.css == ClockSecuritySystem::Enable));
+                clocks.lse_css = true;
             }
 
             clocks.lse = true;
         }
     }
 
+    // Stops the LSE that failed to start and moves its users to the LSI
+    fn fall_back_to_lsi(&self, rcc: &RegisterBlock, clocks: &mut Clocks) {
+        rcc.bdcr.modify(|_, w| w.lseon().clear_bit());
+
+        if !clocks.lsi {
+            rcc.csr.modify(|_, w| w.lsion().set_bit());
+            wait_until(self.wait_hook, || rcc.csr.read().lsirdy().bit_is_set());
+            clocks.lsi = true;
+        }
+
+        // RTCSEL can only be changed by resetting the backup domain. The RTC has not been counting
+        // without the LSE anyway.
+        let bdcr = rcc.bdcr.read();
+        if bdcr.rtcsel().bits() == RtcClockSource::LSE as u8 {
+            rcc.bdcr.modify(|_, w| w.bdrst().set_bit());
+            rcc.bdcr.modify(|_, w| unsafe {
+                w.bdrst()
+                    .clear_bit()
+                    .rtcsel()
+                    .bits(RtcClockSource::LSI as u8)
+                    .rtcen()
+                    .bit(bdcr.rtcen().bit())
+                    .lscosel()
+                    .bit(bdcr.lscosel().bit())
+                    .lscoen()
+                    .bit(bdcr.lscoen().bit())
+            });
+        }
+
+        clocks.lse_fallback = true;
+    }
+
     fn configure_msi(&self, rcc: &RegisterBlock, clocks: &mut Clocks) {
         if let Some(msi) = self.msi {
-            msi.freeze(rcc, self.lse.is_some());
+            rcc.icscr
+                .modify(|_, w| unsafe { w.msitrim().bits(self.msi_trim as u8) });
 
-            clocks.msi = Some(msi)
+            // Only calibrate against an LSE that actually runs
+            msi.freeze(rcc, clocks.lse, self.wait_hook);
+
+            clocks.msi = Some(msi);
+            clocks.msi_calibrated = clocks.lse;
         }
     }
 
     fn setup_hse(&self, rcc: &RegisterBlock, clocks: &mut Clocks) {
         if let Some(hse) = &self.hse {
-            clocks.hse = Some(hse.freeze(rcc));
+            clocks.hse = Some(hse.start(rcc, self.wait_hook));
+            clocks.hse_css = hse.css() != ClockSecuritySystem::Disable;
         }
     }
 
+    #[cfg(not(any(
+        feature = "stm32l471",
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486"
+    )))]
     fn setup_hsi48(&self, rcc: &RegisterBlock, clocks: &mut Clocks) {
         if self.hsi48_on {
-            rcc.crrcr.modify(|_, w| w.hsi48on().set_bit());
-            while rcc.crrcr.read().hsi48rdy().bit_is_clear() {}
+            hsi48::start(rcc, self.wait_hook);
 
             clocks.hsi48 = true;
         }
     }
 
-    fn setup_hsi16(&self, rcc: &RegisterBlock, _clocks: &mut Clocks) {
-        if self.hsi16_on {
-            rcc.cr.write(|w| w.hsion().set_bit());
-            while rcc.cr.read().hsirdy().bit_is_clear() {}
+    // HSI16 is needed if it has been requested, is used by a peripheral or feeds one of the PLLs
+    fn hsi16_required(&self) -> bool {
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        if matches!(&self.pllsai1, Some(pllsai1) if pllsai1.source() == PllSource::HSI16) {
+            return true;
+        }
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        if matches!(&self.pllsai2, Some(pllsai2) if pllsai2.source() == PllSource::HSI16) {
+            return true;
+        }
+
+        self.hsi16_on
+            || self.hsi16_in_stop
+            || self.hsi16_consumers.any()
+            || self.lpuart1_source == Some(UsartClockSource::HSI16)
+            || self.sai1_uses_hsi16()
+            || matches!(self.mco, Some((McoSource::HSI16, _)))
+            || matches!(
+                self.usart1_auto_choice(),
+                Some((UsartClockSource::HSI16, _))
+            )
+            || matches!(&self.pll, Some(pll) if pll.source() == PllSource::HSI16)
+    }
+
+    fn sai1_uses_hsi16(&self) -> bool {
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        return self.sai1_source == Some(SaiClockSource::HSI16)
+            || self.dfsdm1_audio_source == Some(DfsdmAudioClockSource::HSI16);
+        #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9",)))]
+        return false;
+    }
+
+    // MSI is needed if it is kept running on request, drives SYSCLK, is the 48 MHz clock or feeds
+    // one of the PLLs
+    fn msi_required(&self, sysclk: &SysclkConfig) -> bool {
+        if self.msi.is_none() {
+            return false;
+        }
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        if matches!(&self.pllsai1, Some(pllsai1) if pllsai1.source() == PllSource::MSI) {
+            return true;
+        }
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        if matches!(&self.pllsai2, Some(pllsai2) if pllsai2.source() == PllSource::MSI) {
+            return true;
+        }
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        if self.dfsdm1_audio_source == Some(DfsdmAudioClockSource::MSI) {
+            return true;
+        }
+
+        self.keep_msi
+            || sysclk.source_clock == SysclkSource::MSI
+            || self.clk48_source == Some(Clk48ClockSource::MSI)
+            || matches!(self.mco, Some((McoSource::MSI, _)))
+            || matches!(&self.pll, Some(pll) if pll.source() == PllSource::MSI)
+    }
+
+    fn setup_hsi16(&self, rcc: &RegisterBlock, clocks: &mut Clocks) {
+        if self.hsi16_required() {
+            rcc.cr.modify(|_, w| w.hsion().set_bit());
+            wait_until(self.wait_hook, || rcc.cr.read().hsirdy().bit_is_set());
+
+            clocks.hsi16 = true;
+        }
+
+        rcc.cr.modify(|_, w| {
+            w.hsikeron()
+                .bit(self.hsi16_in_stop)
+                .hsiasfs()
+                .bit(self.hsi16_wakeup_start)
+        });
+        clocks.hsi16_in_stop = self.hsi16_in_stop;
+    }
+
+    // PLLM as set explicitly or by the first enabled PLL that uses it
+    fn shared_pll_input_divider(&self) -> Option<u8> {
+        #[cfg(not(any(
+            feature = "stm32l412",
+            feature = "stm32l422",
+            feature = "stm32l4r9",
+            feature = "stm32l4s9",
+        )))]
+        let pllsai1 = self.pllsai1.as_ref().map(|pllsai1| pllsai1.in_div());
+        #[cfg(any(
+            feature = "stm32l412",
+            feature = "stm32l422",
+            feature = "stm32l4r9",
+            feature = "stm32l4s9",
+        ))]
+        let pllsai1 = None;
+
+        self.pll_input_divider
+            .or_else(|| self.pll.as_ref().map(|pll| pll.in_div()))
+            .or(pllsai1)
+    }
+
+    // Source of the first enabled PLL, validated to be the same for all of them
+    fn shared_pll_source(&self) -> Option<PllSource> {
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        if let Some(pllsai1) = &self.pllsai1 {
+            if self.pll.is_none() {
+                return Some(pllsai1.source());
+            }
+        }
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        if let Some(pllsai2) = &self.pllsai2 {
+            if self.pll.is_none() && self.pllsai1.is_none() {
+                return Some(pllsai2.source());
+            }
+        }
+
+        self.pll.as_ref().map(|pll| pll.source())
+    }
+
+    // Returns true if the clock currently driving SYSCLK, directly or through the PLL, is stopped
+    // or changed by `freeze`
+    fn reprograms_sysclk_source(&self, rcc: &RegisterBlock) -> bool {
+        match SysclkSource::from_bits(rcc.cfgr.read().sws().bits()) {
+            // The MSI range can be changed while it drives SYSCLK
+            SysclkSource::MSI | SysclkSource::HSI16 => false,
+            SysclkSource::HSE => self.hse.is_some(),
+            SysclkSource::PLL if self.shared_pll_source().is_some() => true,
+            SysclkSource::PLL => match PllSource::from_bits(rcc.pllcfgr.read().pllsrc().bits()) {
+                Some(PllSource::MSI) => self.msi.is_some(),
+                Some(PllSource::HSI16) => false,
+                Some(PllSource::HSE) => self.hse.is_some(),
+                None => true,
+            },
+        }
+    }
+
+    // Programs the source and input divider all PLLs share, which is only allowed while they are
+    // off. SYSCLK doesn't run on the PLL at this point.
+    fn setup_pll_input(&self, rcc: &RegisterBlock) {
+        let source = match self.shared_pll_source() {
+            Some(source) => source,
+            None => return,
+        };
+
+        pll::stop(rcc, self.wait_hook).expect("SYSCLK has been switched away from the PLL");
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        {
+            rcc.cr.modify(|_, w| w.pllsai1on().clear_bit());
+            wait_until(self.wait_hook, || rcc.cr.read().pllsai1rdy().bit_is_clear());
+        }
+
+        rcc.pllcfgr
+            .modify(|_, w| unsafe { w.pllsrc().bits(source.source_bits()) });
+        if let Some(pllm) = self.shared_pll_input_divider() {
+            rcc.pllcfgr
+                .modify(|_, w| unsafe { w.pllm().bits(pllm - 1) });
         }
     }
 
     fn setup_pll(&self, rcc: &RegisterBlock, clocks: &mut Clocks) {
         if let Some(pll_cfg) = &self.pll {
-            clocks.pll = Some(pll_cfg.freeze(&self, rcc));
+            let source_freq = self
+                .pll_source_freq(pll_cfg.source())
+                .expect("PLL source has been validated");
+            clocks.pll = pll_cfg.start(rcc, self.wait_hook);
+            clocks.pll_source = Some(pll_cfg.source());
+            clocks.pll_vco_input = Some(pll_cfg.vco_input_freq(source_freq));
+            clocks.pll_vco_output = Some(pll_cfg.vco_freq(source_freq));
+        }
+    }
+
+    #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+    fn setup_pllsai1(&self, rcc: &RegisterBlock, clocks: &mut Clocks) {
+        if let Some(pllsai1) = &self.pllsai1 {
+            // The source and, except on the L4+ parts, the input divider are shared with the main
+            // PLL and have been set up by `setup_pll_input`
+            let source_freq = self
+                .pll_source_freq(pllsai1.source())
+                .expect("PLLSAI1 source has been validated");
+            clocks.pllsai1_q = pllsai1.start(source_freq, rcc, self.wait_hook);
+            clocks.pll_source = Some(pllsai1.source());
+        }
+    }
+
+    fn setup_clk48(&self, rcc: &RegisterBlock) {
+        if let Some(source) = self.clk48_source {
+            rcc.ccipr
+                .modify(|_, w| unsafe { w.clk48sel().bits(source.bits()) });
         }
     }
 
+    // Also switches the output off, as `reset_clocks` leaves `RCC_CFGR` alone while SYSCLK runs
+    // on the MSI
+    fn setup_mco(&self, rcc: &RegisterBlock, clocks: &mut Clocks) {
+        let (mcosel, mcopre) = self.mco.map_or((0, 0), |(source, prescaler)| {
+            (source as u8, prescaler as u8)
+        });
+
+        // MCOPRE is missing from the writer of the PAC
+        rcc.cfgr.modify(|r, w| unsafe {
+            w.bits(r.bits() & !(0b111 << 28) | (mcopre as u32) << 28)
+                .mcosel()
+                .bits(mcosel)
+        });
+        clocks.mco = clocks.mco_from_bits(mcosel, mcopre);
+    }
+
+    fn setup_lpuart1(&self, rcc: &RegisterBlock) {
+        if let Some(source) = self.lpuart1_source {
+            rcc.ccipr
+                .modify(|_, w| unsafe { w.lpuart1sel().bits(source.bits()) });
+        }
+    }
+
+    // The DFSDM1 audio clock selection follows the SAI1 one, which it may depend on
+    fn setup_sai1(&self, rcc: &RegisterBlock) {
+        if let Some(source) = self.sai1_source {
+            #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9",)))]
+            rcc.ccipr
+                .modify(|_, w| unsafe { w.sai1sel().bits(source.bits()) });
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            rcc.ccipr2
+                .modify(|_, w| unsafe { w.sai1sel().bits(source.bits()) });
+        }
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        if let Some(source) = self.dfsdm1_audio_source {
+            rcc.ccipr2
+                .modify(|_, w| unsafe { w.adfsdmsel().bits(source.bits()) });
+        }
+    }
+
+    // USART1 kernel clock with the lowest baud rate error, if `auto_usart1_source` is used. Must
+    // only be called on a validated SYSCLK configuration.
+    fn usart1_auto_choice(&self) -> Option<(UsartClockSource, Hertz)> {
+        let baud = self.usart1_target_baud?;
+        let sysclk = self.create_sysclk_config();
+        let pclk2 = self
+            .pclk2_config(self.create_hclk_config(&sysclk).freq())
+            .freq();
+
+        let candidates = [
+            (UsartClockSource::PCLK, pclk2),
+            (UsartClockSource::SYSCLK, sysclk.speed),
+            (UsartClockSource::HSI16, HSI16_FREQ),
+        ];
+        let mut best: Option<(UsartClockSource, Hertz, f32)> = None;
+        for (source, freq) in candidates {
+            if let Some((_, error)) = usart_baud_error(freq, baud) {
+                let error = if error < 0.0 { -error } else { error };
+                if !matches!(best, Some((_, _, best_error)) if best_error <= error) {
+                    best = Some((source, freq, error));
+                }
+            }
+        }
+
+        best.map(|(source, freq, _)| (source, freq))
+    }
+
+    fn setup_usart1(&self, rcc: &RegisterBlock, clocks: &mut Clocks) {
+        if let Some((source, freq)) = self.usart1_auto_choice() {
+            rcc.ccipr
+                .modify(|_, w| unsafe { w.usart1sel().bits(source.bits()) });
+            clocks.usart1_auto_source = Some((source, freq));
+        }
+    }
+
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    fn setup_pllsai2(&self, rcc: &RegisterBlock, clocks: &mut Clocks) {
+        if let Some(pllsai2) = &self.pllsai2 {
+            // The source is shared with the main PLL and has been set up by `setup_pll_input`
+            let source_freq = self
+                .pll_source_freq(pllsai2.source())
+                .expect("PLLSAI2 source has been validated");
+            (clocks.dsi, clocks.ltdc) = pllsai2.start(source_freq, rcc, self.wait_hook);
+        }
+    }
+
+    // The explicit configuration takes precedence over the preferred source, which takes
+    // precedence over the MSI
     fn create_sysclk_config(&self) -> SysclkConfig {
         if let Some(sysclk) = &self.sysclk {
             sysclk.clone()
+        } else if let Some(source) = self.sysclk_preference {
+            SysclkConfig {
+                speed: self
+                    .sysclk_source_freq(source)
+                    .expect("The preferred SYSCLK source is not enabled"),
+                source_clock: source,
+            }
         } else if let Some(msi) = self.msi {
             // Use MSI as default, as per standard
             SysclkConfig {
@@ -256,39 +1469,15 @@ impl CFGR {
         }
     }
     fn setup_sysclk(&self, config: &SysclkConfig, rcc: &RegisterBlock, clocks: &mut Clocks) {
-        // Check that the speed we want is the speed we actually get from our source clock
-        let source_speed = match config.source_clock {
-            SysclkSource::HSE => self
-                .hse
-                .as_ref()
-                .expect("SYSCLK set up on HSE, but HSE is not enabled.")
-                .speed(),
-            SysclkSource::HSI16 => {
-                if self.hsi16_on {
-                    HSI16_FREQ
-                } else {
-                    panic!("SYSCLK set up on HSI16, but HSI16 is not enabled.")
-                }
-            }
-            SysclkSource::MSI => self
-                .msi
-                .as_ref()
-                .expect("SYSCLK set up on MSI, but MSI is not enabled.")
-                .to_hertz(),
-            SysclkSource::PLL => self
-                .pll
-                .as_ref()
-                .expect("SYSCLK set up on PLL, but PLL is not enabled.")
-                .speed(),
-        };
-        assert_eq!(source_speed, config.speed, "The clock feeding SYSCLK does not actually have the correct speed to meet the targeted SYSCLK speed.");
-
         // Set the SYSCLK source
         rcc.cfgr
             .modify(|_, w| unsafe { w.sw().bits(config.source_clock as u8) });
-        while rcc.cfgr.read().sws().bits() != config.source_clock as u8 {}
+        wait_until(self.wait_hook, || {
+            rcc.cfgr.read().sws().bits() == config.source_clock as u8
+        });
 
         clocks.sysclk = config.speed;
+        clocks.sysclk_source = config.source_clock;
     }
 
     fn create_hclk_config(&self, sysclk_config: &SysclkConfig) -> HclkConfig {
@@ -310,81 +1499,306 @@ impl CFGR {
         hclk.freeze(sysclk.speed, rcc);
     }
 
+    // Steps the AHB prescaler from 2^`hclk_ramp_steps` times the final divider down to twice the
+    // final divider. With `first` only the slowest step is set, before SYSCLK is switched.
+    fn ramp_hclk(
+        &self,
+        rcc: &RegisterBlock,
+        hclk: &HclkConfig,
+        sysclk: &SysclkConfig,
+        first: bool,
+    ) {
+        let final_div = HclkDivider::from_ratio(sysclk.speed, hclk.freq()).div_factor() as u32;
+        let steps = (1..=self.hclk_ramp_steps as u32)
+            .rev()
+            .filter_map(|step| HclkDivider::from_div_factor(final_div << step));
+
+        for divider in steps {
+            rcc.cfgr
+                .modify(|_, w| unsafe { w.hpre().bits(divider.bits()) });
+            if first {
+                return;
+            }
+
+            // Let the supply settle for about 1 µs at the current HCLK
+            let step_hclk = sysclk.speed / divider.div_factor() as u32;
+            cortex_m::asm::delay((step_hclk.raw() / 1_000_000).max(1));
+        }
+    }
+
     fn setup_periph_clocks(&self, rcc: &RegisterBlock, hclk: &HclkConfig, clocks: &mut Clocks) {
-        // Use the PCLK configurations or default to the same as HCLK
-        let pclk1_config = match self.pclk1 {
-            Some(config) => config,
-            None => Pclk1Config::new(hclk.freq()),
-        };
-        let pclk2_config = match self.pclk2 {
-            Some(config) => config,
-            None => Pclk2Config::new(hclk.freq()),
-        };
+        self.pclk1_config(hclk.freq())
+            .apply_validated(hclk.freq(), rcc, clocks);
+        self.pclk2_config(hclk.freq())
+            .apply_validated(hclk.freq(), rcc, clocks);
+    }
+
+    // Use the PCLK configurations, derive them from the timer clocks or default to the same as HCLK
+    fn pclk1_config(&self, hclk: Hertz) -> Pclk1Config {
+        let from_timclk = self
+            .timclk1
+            .and_then(|timclk| Prescaler::pclk_for_timclk(hclk, timclk));
 
-        (clocks.pclk1, clocks.timclk1) = pclk1_config.freeze(hclk.freq(), rcc);
-        (clocks.pclk2, clocks.timclk2) = pclk2_config.freeze(hclk.freq(), rcc);
+        self.pclk1
+            .unwrap_or_else(|| Pclk1Config::new(from_timclk.unwrap_or(hclk)))
+            .with_min_prescaler(self.pclk1_min_prescaler)
     }
 
-    fn adjust_flash_wait_states(&self, acr: &mut ACR, hclk: &HclkConfig) {
-        let hclk = hclk.freq();
-        let latency_bits = if hclk.raw() <= 16_000_000 {
-            0b000
-        } else if hclk.raw() <= 32_000_000 {
-            0b001
-        } else if hclk.raw() <= 48_000_000 {
-            0b010
-        } else if hclk.raw() <= 64_000_000 {
-            0b011
-        } else {
-            0b100
-        };
+    fn pclk2_config(&self, hclk: Hertz) -> Pclk2Config {
+        let from_timclk = self
+            .timclk2
+            .and_then(|timclk| Prescaler::pclk_for_timclk(hclk, timclk));
 
-        acr.acr()
-            .write(|w| unsafe { w.latency().bits(latency_bits) })
+        self.pclk2
+            .unwrap_or_else(|| Pclk2Config::new(from_timclk.unwrap_or(hclk)))
+            .with_min_prescaler(self.pclk2_min_prescaler)
     }
 
-    // Disables the MSI, if it is not configured, since it was used during configuration as the backup clock.
-    fn clean_msi(&self, rcc: &RegisterBlock) {
-        if self.msi.is_none() {
-            rcc.cr
-                .modify(|_, w| w.msion().clear_bit().msipllen().clear_bit())
+    // Programs a flash latency that covers both the HCLK currently running and the new one, which
+    // drops to what the new HCLK needs once it has been switched to
+    fn set_flash_wait_states(
+        &self,
+        rcc: &RegisterBlock,
+        acr: &flash::ACR,
+        hclk: &HclkConfig,
+        range: VosRange,
+        clocks: &mut Clocks,
+    ) {
+        let hse = self.hse.as_ref().map(|hse| hse.speed());
+        clocks.flash_latency = ACR::set_wait_states(acr, rcc, hse, hclk.freq(), range)
+            .expect("Failed to set the flash latency");
+    }
+
+    // Disables the MSI, which was used during configuration as the backup clock, if it is not
+    // configured or nothing uses it.
+    fn clean_msi(&self, rcc: &RegisterBlock, sysclk: &SysclkConfig, clocks: &mut Clocks) {
+        if !self.msi_required(sysclk) {
+            msi::stop(rcc, self.wait_hook).expect("MSI still feeds SYSCLK");
+
+            clocks.msi = None;
+            clocks.msi_calibrated = false;
+        }
+    }
+
+    // Disables the HSI16 if it is not needed anymore, whether a previous configuration or the
+    // application started it. This is documented on `freeze` and `enable_hsi16`.
+    fn clean_hsi16(&self, rcc: &RegisterBlock) {
+        if !self.hsi16_required() && ensure_not_sysclk(rcc, SysclkSource::HSI16).is_ok() {
+            rcc.cr.modify(|_, w| w.hsion().clear_bit())
         }
     }
 }
 
-fn reset_clocks(rcc: &RegisterBlock) {
+// Flash wait states required for the given HCLK frequency in the voltage range `range`. HCLK
+// has been checked against the range by `validate_for`.
+pub(super) fn flash_latency_bits(hclk: Hertz, range: VosRange) -> u8 {
+    ACR::latency_for_hclk(hclk, range).expect("HCLK above the voltage range")
+}
+
+/// Recovers from an HSE failure detected by the clock security system
+///
+/// Meant to be called from the NMI handler when the HSE has been enabled with
+/// `ClockSecuritySystem::Enable`. On a failure the hardware already switches the HSE off and
+/// SYSCLK to HSI16. This acknowledges the failure, otherwise the NMI fires again, switches SYSCLK
+/// to the MSI (4 MHz unless it already runs) and stops the PLL, which may have been running on
+/// the HSE. The returned `Clocks` describe the new state and replace the ones from `freeze`,
+/// which are invalid now.
+pub fn handle_hse_css_failure(rcc: &RegisterBlock) -> Clocks {
+    rcc.cicr.write(|w| w.cssc().set_bit());
+
+    reset_clocks(rcc, None);
+    pll::disable(rcc).expect("SYSCLK has been switched to the MSI");
+
+    // NOTE(unsafe) only reads the flash latency
+    let acr = unsafe { &(*FLASH::ptr()).acr };
+    Clocks::from_hardware(rcc, acr, None).unwrap_or_default()
+}
+
+/// Switches SYSCLK back to the 4 MHz MSI and resets the clock configuration register.
+///
+/// Only the MSI bits of `RCC_CR` and `RCC_CFGR` are touched. The backup domain (`RCC_BDCR`), and
+/// with it the LSE and RTC configuration, is never disturbed, so time keeping continues across
+/// multiple calls to `freeze`.
+fn reset_clocks(rcc: &RegisterBlock, wait_hook: Option<WaitHook>) {
     // Switch to MSI as fallback default system clock at 4MHz.
     if rcc.cr.read().msion().bit_is_clear() {
-        rcc.cr.modify(|_, w| {
-            w.msirgsel().set_bit();
-            w.msirange().range4m();
-            w.msipllen().clear_bit();
-            w.msion().set_bit()
-        });
-
-        while rcc.cr.read().msirdy().bit_is_clear() {}
+        MsiFreq::RANGE4M.freeze(rcc, false, wait_hook);
     }
     // Reset clock configuration to default
     if rcc.cfgr.read().sws().bits() != SysclkSource::MSI as u8 {
         rcc.cfgr.reset();
-        while rcc.cfgr.read().sws().bits() != SysclkSource::MSI as u8 {}
+        wait_until(wait_hook, || {
+            rcc.cfgr.read().sws().bits() == SysclkSource::MSI as u8
+        });
     }
 }
 
 impl Default for CFGR {
     fn default() -> Self {
         Self {
+            #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+            pllsai1: None,
             hse: None,
             lse: None,
             msi: None,
+            msi_trim: 0,
             hsi48_on: false,
             hsi16_on: false,
+            hsi16_consumers: Hsi16Consumers::default(),
+            hsi16_in_stop: false,
+            hsi16_wakeup_start: false,
             lsi_on: false,
             hclk: None,
             pclk1: None,
             pclk2: None,
+            timclk1: None,
+            timclk2: None,
+            pclk1_min_prescaler: Prescaler::Div1,
+            pclk2_min_prescaler: Prescaler::Div1,
             sysclk: None,
+            sysclk_preference: None,
             pll: None,
+            pll_input_divider: None,
+            assume_current: false,
+            skip_reset: false,
+            hclk_ramp_steps: 0,
+            wait_hook: None,
+            lse_timeout: None,
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            pllsai2: None,
+            clk48_source: None,
+            lpuart1_source: None,
+            sai1_source: None,
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            dfsdm1_audio_source: None,
+            mco: None,
+            sai_ext_clk: None,
+            usart1_target_baud: None,
+            usb_on_msi: false,
+            keep_msi: false,
+            low_power_plan: LowPowerClockPlan::default(),
+            analog_sleep_gating: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::mock::{self, Event, Osc, READY_AFTER};
+    use super::*;
+
+    #[test]
+    fn freeze_polls_each_clock_until_ready_in_order() {
+        let regs = mock::registers();
+        let cfgr = CFGR::default().hsi16_80mhz().with_wait_hook(mock::poll);
+        cfgr.validate().unwrap();
+
+        let clocks = cfgr.apply(regs.rcc, regs.acr, regs.pwr_cr1, VosRange::HighPerformance);
+
+        // The PLL is only started once its source is ready, and SYSCLK only switched to the locked
+        // PLL after the flash latency has been raised to 4 wait states for 80 MHz. The MSI, which
+        // bridged SYSCLK, is stopped last.
+        assert_eq!(
+            mock::take_events(),
+            [
+                Event::Ready(Osc::Hsi16),
+                Event::Ready(Osc::Pll),
+                Event::Switched {
+                    sw: SysclkSource::PLL as u8,
+                    latency: 4
+                },
+                Event::Stopped(Osc::Msi),
+            ]
+        );
+        assert_eq!(mock::polls(), 2 * READY_AFTER + 2);
+        assert_eq!(clocks.sysclk, Hertz::MHz(80));
+        assert_eq!(clocks.flash_latency, 4);
+        assert!(clocks.msi.is_none());
+    }
+
+    #[test]
+    fn flash_latency_follows_voltage_range() {
+        let regs = mock::registers();
+        let cfgr = CFGR::default()
+            .enable_hsi16(true)
+            .set_sysclk(SysclkSource::HSI16, HSI16_FREQ)
+            .with_wait_hook(mock::poll);
+        cfgr.validate().unwrap();
+
+        // 16 MHz needs no wait state in range 1, but two in range 2
+        let clocks = cfgr.apply(regs.rcc, regs.acr, regs.pwr_cr1, VosRange::LowPower);
+
+        assert_eq!(
+            mock::take_events()[1],
+            Event::Switched {
+                sw: SysclkSource::HSI16 as u8,
+                latency: 2
+            }
+        );
+        assert_eq!(clocks.flash_latency, 2);
+    }
+
+    #[test]
+    fn flash_latency_covers_running_hclk() {
+        let regs = mock::registers();
+        let cfgr = CFGR::default().hsi16_80mhz().with_wait_hook(mock::poll);
+        cfgr.validate().unwrap();
+        cfgr.apply(regs.rcc, regs.acr, regs.pwr_cr1, VosRange::HighPerformance);
+
+        // Before HCLK is lowered, the latency still has to cover the 80 MHz the CPU runs on
+        assert!(matches!(
+            ACR::set_wait_states(
+                regs.acr,
+                regs.rcc,
+                None,
+                HSI16_FREQ,
+                VosRange::HighPerformance
+            ),
+            Ok(4)
+        ));
+        // Neither can it be switched to range 2
+        assert!(matches!(
+            ACR::set_wait_states(regs.acr, regs.rcc, None, HSI16_FREQ, VosRange::LowPower),
+            Err(crate::flash::Error::Illegal)
+        ));
+        assert_eq!(regs.acr.read().latency().bits(), 4);
+    }
+
+    #[test]
+    fn startup_estimate_caps_long_lse_timeout() {
+        let cfgr = CFGR::default()
+            .enable_lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+            .set_lse_timeout(MilliSeconds::millis(u32::MAX));
+
+        // The 2 s LSE startup, followed by the LSI it falls back to
+        assert_eq!(
+            cfgr.estimated_startup_time(),
+            MicroSeconds::micros(2_000_130)
+        );
+    }
+
+    #[test]
+    fn lse_keeps_running_across_freeze() {
+        let regs = mock::registers();
+        let cfgr = || {
+            CFGR::default()
+                .enable_lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+                .hsi16_80mhz()
+                .with_wait_hook(mock::poll)
+        };
+        cfgr().validate().unwrap();
+
+        cfgr().apply(regs.rcc, regs.acr, regs.pwr_cr1, VosRange::HighPerformance);
+        assert_eq!(mock::take_events()[0], Event::Ready(Osc::Lse));
+
+        // The second `freeze` switches SYSCLK back to the MSI and relocks the PLL, but neither
+        // stops nor restarts the LSE
+        let clocks = cfgr().apply(regs.rcc, regs.acr, regs.pwr_cr1, VosRange::HighPerformance);
+        let events = mock::take_events();
+        assert!(events.contains(&Event::Stopped(Osc::Pll)));
+        assert!(!events.contains(&Event::Stopped(Osc::Lse)));
+        assert!(!events.contains(&Event::Ready(Osc::Lse)));
+        assert!(regs.rcc.bdcr.read().lserdy().bit_is_set());
+        assert!(clocks.lse);
+    }
+}
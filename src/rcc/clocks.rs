@@ -1,7 +1,99 @@
-use crate::time::Hertz;
+use crate::pac::flash;
+use crate::pac::rcc::RegisterBlock;
+use crate::pac::{FLASH, RCC};
+use crate::rtc::RtcClockSource;
+use crate::time::{Hertz, MicroSeconds};
+use cortex_m::peripheral::syst::SystClkSource;
 use fugit::RateExtU32;
 
-use super::MsiFreq;
+use super::ccipr::{CciprSnapshot, UsartClockSource};
+use super::hclk::HclkDivider;
+use super::pclk::Prescaler;
+use super::pll::{PllCfgr, PllSource};
+use super::{LowPowerClockPlan, MsiFreq, SysclkSource, HSI16_FREQ, LSE_FREQ, LSI_FREQ};
+
+#[cfg(not(feature = "typed-clocks"))]
+mod typed {
+    use crate::time::Hertz;
+
+    pub type Sysclk = Hertz;
+    pub type Hclk = Hertz;
+    pub type Pclk1 = Hertz;
+    pub type Pclk2 = Hertz;
+    pub type Timclk1 = Hertz;
+    pub type Timclk2 = Hertz;
+}
+
+#[cfg(feature = "typed-clocks")]
+mod typed {
+    use core::cmp::Ordering;
+    use core::ops::Deref;
+
+    use crate::time::Hertz;
+
+    macro_rules! typed_clock {
+        ($($(#[$meta:meta])* $Name:ident,)+) => {
+            $(
+                $(#[$meta])*
+                #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+                pub struct $Name(pub Hertz);
+
+                impl From<Hertz> for $Name {
+                    fn from(freq: Hertz) -> Self {
+                        Self(freq)
+                    }
+                }
+
+                impl From<$Name> for Hertz {
+                    fn from(clock: $Name) -> Self {
+                        clock.0
+                    }
+                }
+
+                impl Deref for $Name {
+                    type Target = Hertz;
+
+                    fn deref(&self) -> &Hertz {
+                        &self.0
+                    }
+                }
+
+                impl PartialEq<Hertz> for $Name {
+                    fn eq(&self, other: &Hertz) -> bool {
+                        self.0 == *other
+                    }
+                }
+
+                impl PartialOrd<Hertz> for $Name {
+                    fn partial_cmp(&self, other: &Hertz) -> Option<Ordering> {
+                        self.0.partial_cmp(other)
+                    }
+                }
+            )+
+        };
+    }
+
+    typed_clock! {
+        /// System (core) clock frequency
+        Sysclk,
+        /// AHB clock frequency
+        Hclk,
+        /// APB1 clock frequency
+        Pclk1,
+        /// APB2 clock frequency
+        Pclk2,
+        /// Frequency of the timers on APB1
+        Timclk1,
+        /// Frequency of the timers on APB2
+        Timclk2,
+    }
+}
+
+/// Bus and core clock frequencies as returned by the `Clocks` getters
+///
+/// With the `typed-clocks` feature these are distinct newtypes around `Hertz`, so drivers can
+/// demand a specific clock in their signatures. Otherwise they are plain `Hertz` aliases.
+pub use typed::{Hclk, Pclk1, Pclk2, Sysclk, Timclk1, Timclk2};
 
 /// Frozen clock frequencies
 ///
@@ -9,25 +101,75 @@ use super::MsiFreq;
 #[derive(Clone, Copy, Debug)]
 pub struct Clocks {
     pub(super) hclk: Hertz,
+    pub(super) hsi16: bool,
+    pub(super) hsi16_in_stop: bool,
     pub(super) hsi48: bool,
     pub(super) msi: Option<MsiFreq>,
+    pub(super) msi_calibrated: bool,
+    pub(super) msi_measured: Option<Hertz>,
     pub(super) lsi: bool,
+    pub(super) lsi_measured: Option<Hertz>,
     pub(super) lse: bool,
+    pub(super) lse_fallback: bool,
+    pub(super) lse_css: bool,
     pub(super) hse: Option<Hertz>,
+    pub(super) hse_css: bool,
     pub(super) pclk1: Hertz,
     pub(super) pclk2: Hertz,
     pub(super) ppre1: u8,
     pub(super) ppre2: u8,
     pub(super) sysclk: Hertz,
+    pub(super) sysclk_source: SysclkSource,
     pub(super) timclk1: Hertz,
     pub(super) timclk2: Hertz,
     pub(super) pll: Option<Hertz>,
+    pub(super) pll_source: Option<PllSource>,
+    pub(super) pll_vco_input: Option<Hertz>,
+    pub(super) pll_vco_output: Option<Hertz>,
+    pub(super) pll_q: Option<Hertz>,
+    pub(super) pllsai1_q: Option<Hertz>,
+    pub(super) flash_latency: u8,
+    pub(super) low_power_plan: LowPowerClockPlan,
+    pub(super) mco: Option<Hertz>,
+    pub(super) sai_ext_clk: Option<Hertz>,
+    pub(super) usart1_auto_source: Option<(UsartClockSource, Hertz)>,
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    pub(super) dsi: Option<Hertz>,
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    pub(super) ltdc: Option<Hertz>,
+}
+
+/// Key frequencies of `Clocks` in kHz, as a plain struct for byte logging or storing in flash
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClockReport {
+    pub sysclk_khz: u32,
+    pub hclk_khz: u32,
+    pub pclk1_khz: u32,
+    pub pclk2_khz: u32,
+    /// PLL output, 0 if the PLL is off
+    pub pll_khz: u32,
 }
 
 impl Clocks {
     /// Returns the frequency of the AHB
-    pub fn hclk(&self) -> Hertz {
-        self.hclk
+    pub fn hclk(&self) -> Hclk {
+        Hclk::from(self.hclk)
+    }
+
+    /// Returns the frequency of the AHB2, which is HCLK on all STM32L4 parts
+    pub fn hclk2(&self) -> Hclk {
+        self.hclk()
+    }
+
+    /// Returns the frequency of the AHB3, which is HCLK on all STM32L4 parts
+    pub fn hclk3(&self) -> Hclk {
+        self.hclk()
+    }
+
+    /// Returns status of HSI16
+    pub fn hsi16(&self) -> bool {
+        self.hsi16
     }
 
     /// Returns status of HSI48
@@ -40,24 +182,110 @@ impl Clocks {
         self.msi
     }
 
+    /// Returns the frequency of the MSI, if it is on
+    ///
+    /// This is the nominal frequency of the range unless a measured frequency was recorded with
+    /// `calibrate_msi`.
+    pub fn msi_hz(&self) -> Option<Hertz> {
+        self.msi
+            .map(|msi| self.msi_measured.unwrap_or_else(|| msi.to_hertz()))
+    }
+
+    /// Returns true if the MSI is trimmed by the LSE (MSI PLL mode)
+    ///
+    /// A calibrated MSI is accurate enough for e.g. crystal-less USB at 48 MHz.
+    pub fn msi_is_calibrated(&self) -> bool {
+        self.msi_calibrated
+    }
+
     /// Returns status of the LSI
     pub fn lsi(&self) -> bool {
         self.lsi
     }
 
+    /// Returns the frequency of the LSI, if it is on
+    ///
+    /// This is the nominal 32 kHz unless a measured frequency was recorded with
+    /// `with_measured_lsi` or `calibrate_lsi`. The LSI varies by several percent over voltage and temperature, so
+    /// IWDG and RTC periods derived from the nominal frequency are rough estimates.
+    pub fn lsi_hz(&self) -> Option<Hertz> {
+        self.lsi.then(|| self.lsi_measured.unwrap_or(LSI_FREQ))
+    }
+
+    /// Records the LSI frequency measured against an accurate clock, e.g. with a timer input
+    /// capture, to be returned by `lsi_hz`
+    pub fn with_measured_lsi(mut self, freq: Hertz) -> Self {
+        self.lsi_measured = Some(freq);
+        self
+    }
+
     // Return the status of the LSE
     pub fn lse(&self) -> bool {
         self.lse
     }
 
+    /// Returns true if the clock security system has detected a failure of the LSE
+    /// (`RCC_BDCR.LSECSSD`)
+    ///
+    /// The flag stays set until the backup domain is reset.
+    pub fn lse_failure_detected(&self) -> bool {
+        // NOTE(unsafe) atomic read with no side effects
+        let bdcr = unsafe { (*RCC::ptr()).bdcr.read() };
+
+        self.lse && bdcr.lsecssd().bit_is_set()
+    }
+
+    /// Returns true if the clock security system monitors the LSE (`RCC_BDCR.LSECSSON`)
+    pub fn lse_css_enabled(&self) -> bool {
+        self.lse_css
+    }
+
+    /// Returns the frequency of the LSE (32.768 kHz), if it is on
+    pub fn lse_hz(&self) -> Option<Hertz> {
+        self.lse.then_some(LSE_FREQ)
+    }
+
+    /// Returns true if the LSE did not start within the timeout and the LSI took its place
+    pub fn lse_fallback(&self) -> bool {
+        self.lse_fallback
+    }
+
+    /// Returns true if the RTC runs on a ready LSE, the precondition for the RTC smooth calibration
+    ///
+    /// Calibrating against the imprecise LSI is pointless, so this is false for any other RTC
+    /// clock source.
+    pub fn rtc_calibration_ready(&self) -> bool {
+        // NOTE(unsafe) atomic read with no side effects
+        let bdcr = unsafe { (*RCC::ptr()).bdcr.read() };
+
+        self.lse && bdcr.lserdy().bit_is_set() && bdcr.rtcsel().bits() == RtcClockSource::LSE as u8
+    }
+
+    /// Returns the HSE frequency declared in `CFGR::enable_hse`, if the HSE is running
+    ///
+    /// The HAL cannot measure the HSE, all derived frequencies rely on this value being correct.
+    /// Downstream code can assert on it to catch e.g. a 16 MHz crystal declared as 8 MHz.
+    pub fn hse_declared(&self) -> Option<Hertz> {
+        self.hse
+    }
+
+    /// Returns true if the clock security system monitors the HSE (`RCC_CR.CSSON`), see
+    /// `handle_hse_css_failure`
+    ///
+    /// CSSON can't be read back, so this is only known if `freeze` has enabled the HSE, and is
+    /// false for clocks taken over with `CFGR::assume_current`.
+    pub fn hse_css_enabled(&self) -> bool {
+        self.hse_css
+    }
+
     /// Returns the frequency of the APB1
-    pub fn pclk1(&self) -> Hertz {
-        self.pclk1
+    pub fn pclk1(&self) -> Pclk1 {
+        Pclk1::from(self.pclk1)
     }
 
     /// Returns the frequency of the APB2
-    pub fn pclk2(&self) -> Hertz {
-        self.pclk2
+    pub fn pclk2(&self) -> Pclk2 {
+        Pclk2::from(self.pclk2)
     }
 
     /// Get PLL output frequency, if it is active
@@ -65,6 +293,103 @@ impl Clocks {
         self.pll
     }
 
+    /// Get the clock feeding the PLLs, if one of them is active
+    pub fn pll_source(&self) -> Option<PllSource> {
+        self.pll_source
+    }
+
+    /// Get the PLL VCO input frequency (source clock divided by PLLM), if the PLL is active
+    pub fn pll_vco_input(&self) -> Option<Hertz> {
+        self.pll_vco_input
+    }
+
+    /// Get the PLL VCO output frequency, if the PLL is active
+    pub fn pll_vco_output(&self) -> Option<Hertz> {
+        self.pll_vco_output
+    }
+
+    /// Get the frequency of the PLL Q output, if it is enabled
+    pub fn pll_q(&self) -> Option<Hertz> {
+        self.pll_q
+    }
+
+    /// Get the frequency of the PLLSAI1 Q output, if it is enabled
+    pub fn pllsai1_q(&self) -> Option<Hertz> {
+        self.pllsai1_q
+    }
+
+    /// Returns the DSI clock provided by PLLSAI2, if it is enabled
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    pub fn dsi(&self) -> Option<Hertz> {
+        self.dsi
+    }
+
+    /// Returns the LTDC pixel clock provided by PLLSAI2, if it is enabled
+    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+    pub fn ltdc(&self) -> Option<Hertz> {
+        self.ltdc
+    }
+
+    /// Returns the number of flash wait states
+    pub fn flash_latency(&self) -> u8 {
+        self.flash_latency
+    }
+
+    /// Reads the kernel clock source of every peripheral with an independent clock and resolves
+    /// the resulting frequencies, e.g. to dump the clock tree for diagnostics
+    pub fn ccipr_snapshot(&self) -> CciprSnapshot {
+        CciprSnapshot::read(self)
+    }
+
+    /// Returns all known frequencies as `(name, frequency)` pairs, e.g. to log or compare the
+    /// whole clock tree in one loop
+    ///
+    /// The bus clocks always come first, clocks that are off or unknown are skipped. The names are
+    /// the ones of the getters.
+    pub fn iter_frequencies(&self) -> impl Iterator<Item = (&'static str, Hertz)> {
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        let (dsi, ltdc) = (self.dsi, self.ltdc);
+        #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9",)))]
+        let (dsi, ltdc) = (None, None);
+
+        let frequencies = [
+            ("sysclk", Some(self.sysclk)),
+            ("hclk", Some(self.hclk)),
+            ("pclk1", Some(self.pclk1)),
+            ("pclk2", Some(self.pclk2)),
+            ("timclk1", Some(self.timclk1)),
+            ("timclk2", Some(self.timclk2)),
+            ("msi", self.msi_hz()),
+            ("hsi16", self.hsi16.then_some(HSI16_FREQ)),
+            ("hsi48", self.hsi48.then_some(Hertz::MHz(48))),
+            ("hse", self.hse),
+            ("lsi", self.lsi_hz()),
+            ("lse", self.lse_hz()),
+            ("pll", self.pll),
+            ("pll_vco_input", self.pll_vco_input),
+            ("pll_vco_output", self.pll_vco_output),
+            ("pll_q", self.pll_q),
+            ("pllsai1_q", self.pllsai1_q),
+            ("dsi", dsi),
+            ("ltdc", ltdc),
+            ("mco", self.mco),
+        ];
+
+        IntoIterator::into_iter(frequencies).filter_map(|(name, freq)| Some((name, freq?)))
+    }
+
+    /// Projects the key frequencies into a `ClockReport`, which avoids formatting `Hertz` on the
+    /// device
+    pub fn to_report(&self) -> ClockReport {
+        ClockReport {
+            sysclk_khz: self.sysclk.to_kHz(),
+            hclk_khz: self.hclk.to_kHz(),
+            pclk1_khz: self.pclk1.to_kHz(),
+            pclk2_khz: self.pclk2.to_kHz(),
+            pll_khz: self.pll.map_or(0, |pll| pll.to_kHz()),
+        }
+    }
+
     // TODO remove `allow`
     #[allow(dead_code)]
     pub(crate) fn ppre1(&self) -> u8 {
@@ -77,18 +402,310 @@ impl Clocks {
     }
 
     /// Returns the system (core) frequency
-    pub fn sysclk(&self) -> Hertz {
-        self.sysclk
+    pub fn sysclk(&self) -> Sysclk {
+        Sysclk::from(self.sysclk)
+    }
+
+    /// Returns true if `source` is the clock SYSCLK is switched to
+    ///
+    /// If this is the PLL, its input clock feeds SYSCLK as well, which the `disable` helpers of
+    /// the oscillators check on their own.
+    pub fn is_sysclk_source(&self, source: SysclkSource) -> bool {
+        self.sysclk_source == source
+    }
+
+    /// Returns the clocks the MCU runs on after a wakeup from Stop and Standby
+    pub fn low_power_clock_plan(&self) -> LowPowerClockPlan {
+        self.low_power_plan
+    }
+
+    /// Returns the USART1 kernel clock chosen by `CFGR::auto_usart1_source` and its frequency
+    pub fn usart1_auto_source(&self) -> Option<(UsartClockSource, Hertz)> {
+        self.usart1_auto_source
     }
 
     /// Returns the frequency for timers on APB1
-    pub fn timclk1(&self) -> Hertz {
-        self.timclk1
+    pub fn timclk1(&self) -> Timclk1 {
+        Timclk1::from(self.timclk1)
     }
 
     /// Returns the frequency for timers on APB2
-    pub fn timclk2(&self) -> Hertz {
-        self.timclk2
+    pub fn timclk2(&self) -> Timclk2 {
+        Timclk2::from(self.timclk2)
+    }
+
+    /// Returns the SysTick processor clock, HCLK, which is used with `SystClkSource::Core`
+    /// (`SYST_CSR.CLKSOURCE` = 1)
+    pub fn systick_core_clk(&self) -> Hertz {
+        self.hclk
+    }
+
+    /// Returns the frequency of the SysTick external reference clock (HCLK / 8), which is used
+    /// with `SystClkSource::External` (`SYST_CSR.CLKSOURCE` = 0)
+    pub fn hclk_div8(&self) -> Hertz {
+        self.hclk / 8
+    }
+
+    /// Returns the clock SysTick counts on with the given `SYST_CSR.CLKSOURCE` selection
+    pub fn systick_clk(&self, source: SystClkSource) -> Hertz {
+        match source {
+            SystClkSource::Core => self.systick_core_clk(),
+            SystClkSource::External => self.hclk_div8(),
+        }
+    }
+
+    /// Returns the SysTick reload value for the given period
+    ///
+    /// SysTick counts either on the core clock (HCLK) or on the external reference (HCLK / 8).
+    /// Returns `None` if the period is shorter than a single tick or does not fit into the 24 bit
+    /// reload register.
+    pub fn systick_reload_for(&self, period: MicroSeconds, source: SystClkSource) -> Option<u32> {
+        // The SysTick Reload Value register supports values between 1 and 0x00FFFFFF.
+        const MAX_RVR: u64 = 0x00FF_FFFF;
+
+        let clock = self.systick_clk(source).raw();
+        let ticks = clock as u64 * period.ticks() as u64 / 1_000_000;
+
+        match ticks.checked_sub(1) {
+            Some(reload) if (1..=MAX_RVR).contains(&reload) => Some(reload as u32),
+            _ => None,
+        }
+    }
+}
+
+/// Clocks that differ between two `Clocks` values, see `Clocks::diff`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClockDiff {
+    pub sysclk_source: bool,
+    pub sysclk: bool,
+    pub hclk: bool,
+    pub pclk1: bool,
+    pub pclk2: bool,
+    pub timclk1: bool,
+    pub timclk2: bool,
+    /// Source or any output of the main PLL
+    pub pll: bool,
+    /// Q output of PLLSAI1
+    pub pllsai1_q: bool,
+    pub flash_latency: bool,
+    pub msi: bool,
+    pub hsi16: bool,
+    pub hsi48: bool,
+    pub hse: bool,
+    pub lse: bool,
+    pub lsi: bool,
+}
+
+impl ClockDiff {
+    /// Returns true if nothing changed
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Clocks {
+    /// Returns which clocks differ between `self` and `other`, e.g. before and after a
+    /// reconfiguration
+    pub fn diff(&self, other: &Clocks) -> ClockDiff {
+        ClockDiff {
+            sysclk_source: self.sysclk_source != other.sysclk_source,
+            sysclk: self.sysclk != other.sysclk,
+            hclk: self.hclk != other.hclk,
+            pclk1: self.pclk1 != other.pclk1,
+            pclk2: self.pclk2 != other.pclk2,
+            timclk1: self.timclk1 != other.timclk1,
+            timclk2: self.timclk2 != other.timclk2,
+            pll: self.pll != other.pll
+                || self.pll_q != other.pll_q
+                || self.pll_source != other.pll_source
+                || self.pll_vco_output != other.pll_vco_output,
+            pllsai1_q: self.pllsai1_q != other.pllsai1_q,
+            flash_latency: self.flash_latency != other.flash_latency,
+            msi: self.msi_hz() != other.msi_hz() || self.msi_calibrated != other.msi_calibrated,
+            hsi16: self.hsi16 != other.hsi16,
+            hsi48: self.hsi48 != other.hsi48,
+            hse: self.hse != other.hse,
+            lse: self.lse != other.lse,
+            lsi: self.lsi_hz() != other.lsi_hz(),
+        }
+    }
+
+    /// Returns true if the clock tree in hardware still matches these `Clocks`
+    ///
+    /// SYSCLK and its source, the bus clocks, the PLL output, the flash latency and the HSE are
+    /// compared. `hse_hint` is the HSE frequency, as for `read_clocks`.
+    pub fn matches_hardware(&self, hse_hint: Option<Hertz>) -> bool {
+        // NOTE(unsafe) atomic reads with no side effects
+        let rcc = unsafe { &*RCC::ptr() };
+        let acr = unsafe { &(*FLASH::ptr()).acr };
+        let live = match Self::from_hardware(rcc, acr, hse_hint) {
+            Some(live) => live,
+            None => return false,
+        };
+
+        live.sysclk_source == self.sysclk_source
+            && live.sysclk == self.sysclk
+            && live.hclk == self.hclk
+            && live.pclk1 == self.pclk1
+            && live.pclk2 == self.pclk2
+            && live.pll == self.pll
+            && live.flash_latency == self.flash_latency
+            && live.hse == self.hse
+    }
+
+    /// Panics if the clock tree has been changed behind the HAL's back since `freeze`
+    ///
+    /// The STM32L4 RCC has no write protection, so raw register access can't be prevented. This
+    /// catches it after the fact, see `matches_hardware`.
+    #[track_caller]
+    pub fn assert_unchanged(&self, hse_hint: Option<Hertz>) {
+        assert!(
+            self.matches_hardware(hse_hint),
+            "The clock configuration in hardware differs from the frozen one"
+        );
+    }
+
+    /// Reads the clock configuration that is currently active in hardware.
+    ///
+    /// The HSE frequency can not be measured and has to be provided if HSE is running. Returns
+    /// `None` if a running clock can not be determined.
+    pub(crate) fn from_hardware(
+        rcc: &RegisterBlock,
+        acr: &flash::ACR,
+        hse: Option<Hertz>,
+    ) -> Option<Self> {
+        let cr = rcc.cr.read();
+        let cfgr = rcc.cfgr.read();
+
+        let msi = if cr.msirdy().bit_is_set() {
+            Some(MsiFreq::from_hardware(rcc))
+        } else {
+            None
+        };
+
+        let hse = if cr.hserdy().bit_is_set() {
+            Some(hse?)
+        } else {
+            None
+        };
+
+        let pllcfgr = PllCfgr::from_register(rcc);
+        let pll_source_freq = match pllcfgr.source {
+            Some(PllSource::MSI) => msi.map(|msi| msi.to_hertz()),
+            Some(PllSource::HSI16) => Some(HSI16_FREQ),
+            Some(PllSource::HSE) => hse,
+            None => None,
+        };
+        let div_factor = |bits: u8| (bits as u32 + 1) * 2;
+
+        let (pll_vco_input, pll_vco_output, pll, pll_q) = if cr.pllrdy().bit_is_set() {
+            let vco_input = pll_source_freq? / pllcfgr.m as u32;
+            let vco_freq = vco_input * pllcfgr.n as u32;
+
+            (
+                Some(vco_input),
+                Some(vco_freq),
+                pllcfgr
+                    .r_enable
+                    .then(|| vco_freq / pllcfgr.r.div_factor() as u32),
+                pllcfgr
+                    .q_enable
+                    .then(|| vco_freq / pllcfgr.q.div_factor() as u32),
+            )
+        } else {
+            (None, None, None, None)
+        };
+
+        let pllsai1cfgr = rcc.pllsai1cfgr.read();
+        let pllsai1_q = if cr.pllsai1rdy().bit_is_set() && pllsai1cfgr.pllsai1qen().bit_is_set() {
+            #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9",)))]
+            let pllsai1m = pllcfgr.m - 1;
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            let pllsai1m = pllsai1cfgr.pllsai1m().bits();
+
+            let vco_freq =
+                pll_source_freq? / (pllsai1m as u32 + 1) * pllsai1cfgr.pllsai1n().bits() as u32;
+            Some(vco_freq / div_factor(pllsai1cfgr.pllsai1q().bits()))
+        } else {
+            None
+        };
+
+        let pll_source = (cr.pllrdy().bit_is_set() || cr.pllsai1rdy().bit_is_set())
+            .then_some(pllcfgr.source)
+            .flatten();
+
+        let sysclk_source = SysclkSource::from_bits(cfgr.sws().bits());
+        let sysclk = match sysclk_source {
+            SysclkSource::MSI => msi?.to_hertz(),
+            SysclkSource::HSI16 => HSI16_FREQ,
+            SysclkSource::HSE => hse?,
+            SysclkSource::PLL => pll?,
+        };
+
+        let hclk = sysclk / HclkDivider::from_bits(cfgr.hpre().bits()).div_factor() as u32;
+        let ppre1 = Prescaler::from_bits(cfgr.ppre1().bits());
+        let ppre2 = Prescaler::from_bits(cfgr.ppre2().bits());
+        let pclk1 = hclk / ppre1.div_factor() as u32;
+        let pclk2 = hclk / ppre2.div_factor() as u32;
+
+        let mut clocks = Self {
+            hclk,
+            hsi16: cr.hsirdy().bit_is_set(),
+            hsi16_in_stop: cr.hsikeron().bit_is_set(),
+            hsi48: cfg!(not(any(
+                feature = "stm32l471",
+                feature = "stm32l475",
+                feature = "stm32l476",
+                feature = "stm32l486"
+            ))) && rcc.crrcr.read().hsi48rdy().bit_is_set(),
+            msi,
+            msi_measured: None,
+            msi_calibrated: msi.is_some()
+                && cr.msipllen().bit_is_set()
+                && rcc.bdcr.read().lserdy().bit_is_set(),
+            lsi: rcc.csr.read().lsirdy().bit_is_set(),
+            lsi_measured: None,
+            lse: rcc.bdcr.read().lserdy().bit_is_set(),
+            lse_fallback: false,
+            lse_css: rcc.bdcr.read().lsecsson().bit_is_set(),
+            // The PAC has no reader for CSSON (bit 19), it reads back as set once enabled though
+            hse_css: cr.bits() & (1 << 19) != 0,
+            flash_latency: acr.read().latency().bits(),
+            low_power_plan: LowPowerClockPlan::from_hardware(rcc),
+            mco: None,
+            sai_ext_clk: None,
+            usart1_auto_source: None,
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            dsi: None,
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            ltdc: None,
+            hse,
+            pclk1,
+            pclk2,
+            ppre1: ppre1.div_factor() as u8,
+            ppre2: ppre2.div_factor() as u8,
+            sysclk,
+            sysclk_source,
+            timclk1: if ppre1 == Prescaler::Div1 {
+                pclk1
+            } else {
+                pclk1 * 2
+            },
+            timclk2: if ppre2 == Prescaler::Div1 {
+                pclk2
+            } else {
+                pclk2 * 2
+            },
+            pll,
+            pll_source,
+            pll_vco_input,
+            pll_vco_output,
+            pll_q,
+            pllsai1_q,
+        };
+        clocks.mco = clocks.mco_from_bits(cfgr.mcosel().bits(), cfgr.mcopre().bits());
+
+        Some(clocks)
     }
 }
 
@@ -96,19 +713,42 @@ impl Default for Clocks {
     fn default() -> Self {
         Self {
             hclk: 4.MHz(),
+            hsi16: false,
+            hsi16_in_stop: false,
             hsi48: false,
             msi: Some(MsiFreq::RANGE4M),
+            msi_measured: None,
+            msi_calibrated: false,
             lsi: false,
+            lsi_measured: None,
             lse: false,
+            lse_fallback: false,
+            lse_css: false,
             hse: None,
+            hse_css: false,
             pclk1: 4.MHz(),
             pclk2: 4.MHz(),
             ppre1: 1,
             ppre2: 1,
             sysclk: 4.MHz(),
+            sysclk_source: SysclkSource::MSI,
             timclk1: 4.MHz(),
             timclk2: 4.MHz(),
             pll: None,
+            pll_source: None,
+            pll_vco_input: None,
+            pll_vco_output: None,
+            pll_q: None,
+            pllsai1_q: None,
+            flash_latency: 0,
+            low_power_plan: LowPowerClockPlan::default(),
+            mco: None,
+            sai_ext_clk: None,
+            usart1_auto_source: None,
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            dsi: None,
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+            ltdc: None,
         }
     }
 }
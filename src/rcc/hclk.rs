@@ -17,21 +17,92 @@ pub enum HclkDivider {
 }
 
 impl HclkDivider {
+    /// All dividers the hardware has, in ascending order
+    pub const ALL: [Self; 9] = [
+        Self::Div1,
+        Self::Div2,
+        Self::Div4,
+        Self::Div8,
+        Self::Div16,
+        Self::Div64,
+        Self::Div128,
+        Self::Div256,
+        Self::Div512,
+    ];
+
+    /// Returns the divider that derives `target` from `source`, if the hardware has one
+    ///
+    /// Note that there is no divider for 32, see `is_div32`.
+    pub fn try_from_ratio(source: Hertz, target: Hertz) -> Option<Self> {
+        if target.raw() == 0 || source.raw() % target.raw() != 0 {
+            return None;
+        }
+
+        Self::from_div_factor(source / target)
+    }
+
+    /// Returns the divider whose output is closest to `target`, together with that output
+    ///
+    /// Unlike `from_ratio` this never panics. A target of `source / 32` falls into the gap
+    /// between /16 and /64 and snaps to /64, whose output is closer. On a tie the divider with
+    /// the lower output is chosen, so HCLK does not end up above `target`.
+    pub fn nearest_from_ratio(source: Hertz, target: Hertz) -> (Self, Hertz) {
+        let mut best = (Self::Div512, source / 512);
+        for divider in Self::ALL.iter().rev() {
+            let freq = source / u32::from(divider.div_factor());
+            let error = freq.raw().abs_diff(target.raw());
+            if error < best.1.raw().abs_diff(target.raw()) {
+                best = (*divider, freq);
+            }
+        }
+
+        best
+    }
+
+    /// Returns the divider with the given division factor, if the hardware has one
+    pub fn from_div_factor(factor: u32) -> Option<Self> {
+        match factor {
+            1 => Some(Self::Div1),
+            2 => Some(Self::Div2),
+            4 => Some(Self::Div4),
+            8 => Some(Self::Div8),
+            16 => Some(Self::Div16),
+            64 => Some(Self::Div64),
+            128 => Some(Self::Div128),
+            256 => Some(Self::Div256),
+            512 => Some(Self::Div512),
+            _ => None,
+        }
+    }
+
+    /// Returns true if `target` is `source` divided by 32, for which the hardware has no divider
+    pub fn is_div32(source: Hertz, target: Hertz) -> bool {
+        target.raw() != 0 && target.raw().checked_mul(32) == Some(source.raw())
+    }
+
+    #[track_caller]
     pub fn from_ratio(source: Hertz, target: Hertz) -> Self {
-        assert!(source.raw() % target.raw() == 0);
-
-        match source / target {
-            0 => panic!("SYSCLK must not be 0. Unable to create HCLK Divider"),
-            1 => Self::Div1,
-            2 => Self::Div2,
-            4 => Self::Div4,
-            8 => Self::Div8,
-            16 => Self::Div16,
-            64 => Self::Div64,
-            128 => Self::Div128,
-            256 => Self::Div256,
-            512 => Self::Div512,
-	    _ => panic!("HCLK can only be set to a value that is SYSCLK divided by a power of 2 less or equals to 512 and not 32")
+        assert!(target.raw() != 0, "HCLK must not be 0 Hz");
+        assert!(
+            !Self::is_div32(source, target),
+            "AHB prescaler /32 is not supported by hardware; valid dividers are 1,2,4,8,16,64,128,256,512"
+        );
+        Self::try_from_ratio(source, target).expect(
+            "HCLK can only be set to a value that is SYSCLK divided by a power of 2 less or equals to 512 and not 32",
+        )
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        match bits {
+            0b1000 => Self::Div2,
+            0b1001 => Self::Div4,
+            0b1010 => Self::Div8,
+            0b1011 => Self::Div16,
+            0b1100 => Self::Div64,
+            0b1101 => Self::Div128,
+            0b1110 => Self::Div256,
+            0b1111 => Self::Div512,
+            _ => Self::Div1,
         }
     }
 
@@ -85,3 +156,48 @@ impl HclkConfig {
             .modify(|_, w| unsafe { w.hpre().bits(divider.bits()) });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_from_ratio_snaps_div32_to_div64() {
+        // 2.5 MHz is 80 MHz / 32, /64 gives 1.25 MHz and /16 5 MHz
+        assert_eq!(
+            HclkDivider::nearest_from_ratio(Hertz::MHz(80), Hertz::kHz(2_500)),
+            (HclkDivider::Div64, Hertz::kHz(1_250))
+        );
+    }
+
+    #[test]
+    fn nearest_from_ratio_picks_lower_output_on_tie() {
+        // 60 MHz is 20 MHz away from both 80 MHz and 40 MHz
+        assert_eq!(
+            HclkDivider::nearest_from_ratio(Hertz::MHz(80), Hertz::MHz(60)),
+            (HclkDivider::Div2, Hertz::MHz(40))
+        );
+    }
+
+    #[test]
+    fn nearest_from_ratio_caps_target_at_source() {
+        assert_eq!(
+            HclkDivider::nearest_from_ratio(Hertz::MHz(16), Hertz::MHz(80)),
+            (HclkDivider::Div1, Hertz::MHz(16))
+        );
+    }
+
+    #[test]
+    fn try_from_ratio_rejects_zero_target() {
+        assert_eq!(
+            HclkDivider::try_from_ratio(Hertz::MHz(80), Hertz::Hz(0)),
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "HCLK must not be 0 Hz")]
+    fn from_ratio_panics_on_zero_target() {
+        HclkDivider::from_ratio(Hertz::MHz(80), Hertz::Hz(0));
+    }
+}
@@ -1,7 +1,22 @@
 use crate::pac::rcc::RegisterBlock;
 use crate::time::Hertz;
 
-use super::{ClockSecuritySystem, CrystalBypass};
+use super::{
+    ensure_not_sysclk, wait_until, ClockError, ClockSecuritySystem, CrystalBypass, SysclkSource,
+    WaitHook,
+};
+
+/// Switches the HSE off
+///
+/// Fails without touching the HSE if it feeds SYSCLK, directly or through the PLL.
+pub fn disable(rcc: &RegisterBlock) -> Result<(), ClockError> {
+    ensure_not_sysclk(rcc, SysclkSource::HSE)?;
+
+    rcc.cr.modify(|_, w| w.hseon().clear_bit());
+    wait_until(None, || rcc.cr.read().hserdy().bit_is_clear());
+
+    Ok(())
+}
 
 #[derive(Debug, PartialEq)]
 /// HSE Configuration
@@ -24,21 +39,61 @@ impl HseConfig {
         self.speed
     }
 
-    pub fn freeze(&self, rcc: &RegisterBlock) -> Hertz {
-        rcc.cr.write(|w| {
-            w.hseon().set_bit();
+    pub fn bypass(&self) -> CrystalBypass {
+        self.bypass
+    }
+
+    pub fn css(&self) -> ClockSecuritySystem {
+        self.css
+    }
+
+    /// Checks the declared frequency against the input mode
+    ///
+    /// The HAL has no way to measure the HSE, so this only catches frequencies outside of what the
+    /// mode supports: 4 to 48 MHz for a crystal or resonator, 1 to 48 MHz for an external clock in
+    /// bypass mode. The STM32L4 has a single bypass mode for any external clock, be it an
+    /// oscillator module or the digital output of an FPGA or another MCU. Its duty cycle has to
+    /// be within 45 % to 55 %, which can't be checked in software.
+    ///
+    /// The clock security system of the HSE can't be enabled without its interrupt, so
+    /// `ClockSecuritySystem::EnableWithoutInterrupt` is rejected as well.
+    pub fn validate(&self) -> Result<(), ClockError> {
+        if self.css == ClockSecuritySystem::EnableWithoutInterrupt {
+            return Err(ClockError::HseCssWithoutInterrupt);
+        }
+
+        let min = match self.bypass {
+            CrystalBypass::Disable => Hertz::MHz(4),
+            CrystalBypass::Enable => Hertz::MHz(1),
+        };
 
-            if self.bypass == CrystalBypass::Enable {
-                w.hsebyp().set_bit();
-            }
+        if (min..=Hertz::MHz(48)).contains(&self.speed) {
+            Ok(())
+        } else {
+            Err(ClockError::HseFreqOutOfRange)
+        }
+    }
+
+    pub fn freeze(&self, rcc: &RegisterBlock, wait_hook: Option<WaitHook>) -> Hertz {
+        self.validate().expect("Invalid HSE configuration");
+        self.start(rcc, wait_hook)
+    }
 
-            w
-        });
+    // The unchecked part of `freeze`. `CFGR::freeze` calls this directly, as `CFGR::validate`
+    // has already checked the HSE.
+    pub(super) fn start(&self, rcc: &RegisterBlock, wait_hook: Option<WaitHook>) -> Hertz {
+        // HSEBYP can only be changed while the HSE is off. The other oscillators are left alone,
+        // the MSI in particular may be driving SYSCLK right now.
+        rcc.cr.modify(|_, w| w.hseon().clear_bit());
+        wait_until(wait_hook, || rcc.cr.read().hserdy().bit_is_clear());
+        rcc.cr
+            .modify(|_, w| w.hsebyp().bit(self.bypass == CrystalBypass::Enable));
+        rcc.cr.modify(|_, w| w.hseon().set_bit());
 
-        while rcc.cr.read().hserdy().bit_is_clear() {}
+        wait_until(wait_hook, || rcc.cr.read().hserdy().bit_is_set());
 
         // Setup CSS
-        if self.css == ClockSecuritySystem::Enable {
+        if self.css != ClockSecuritySystem::Disable {
             // Enable CSS
             rcc.cr.modify(|_, w| w.csson().set_bit());
         }
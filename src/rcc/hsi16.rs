@@ -0,0 +1,49 @@
+//! Runtime trimming of the 16 MHz high speed internal oscillator (`RCC_ICSCR.HSITRIM`)
+//!
+//! The trim value is added to the factory calibration in `RCC_ICSCR.HSICAL`, e.g. to compensate
+//! the drift over temperature. It takes effect immediately, also while the HSI16 drives SYSCLK.
+
+use crate::pac::rcc::RegisterBlock;
+
+/// Largest trim value, the field is 7 bits wide on the L49x/L4Ax and L4+ parts and 5 bits wide
+/// on the others
+#[cfg(any(
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+pub const MAX_TRIM: u8 = 0x7f;
+/// Largest trim value, the field is 7 bits wide on the L49x/L4Ax and L4+ parts and 5 bits wide
+/// on the others
+#[cfg(not(any(
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+)))]
+pub const MAX_TRIM: u8 = 0x1f;
+
+/// Trim value after reset, which leaves the factory calibration unchanged
+pub const DEFAULT_TRIM: u8 = MAX_TRIM / 2 + 1;
+
+/// Returns the current trim value
+pub fn read_trim(rcc: &RegisterBlock) -> u8 {
+    rcc.icscr.read().hsitrim().bits()
+}
+
+/// Sets the trim value, `DEFAULT_TRIM` restores the factory calibration
+///
+/// # Panics
+///
+/// Panics if `trim` exceeds `MAX_TRIM`.
+pub fn set_trim(rcc: &RegisterBlock, trim: u8) {
+    assert!(trim <= MAX_TRIM, "HSI16 trim value out of range");
+
+    rcc.icscr.modify(|_, w| unsafe { w.hsitrim().bits(trim) });
+}
+
+/// Returns the effective calibration (`RCC_ICSCR.HSICAL`), the factory value adjusted by the trim
+pub fn read_calibration(rcc: &RegisterBlock) -> u8 {
+    rcc.icscr.read().hsical().bits()
+}
@@ -0,0 +1,30 @@
+//! 48 MHz high speed internal oscillator for USB, RNG and SDMMC
+//!
+//! Besides enabling it once with `CFGR::enable_hsi48`, the HSI48 can be switched on and off at
+//! runtime, e.g. around USB enumeration. `Clocks::hsi48` only reflects the state after `freeze`.
+
+use crate::pac::rcc::RegisterBlock;
+
+use super::{wait_until, WaitHook};
+
+/// Enables the HSI48 and waits until it is ready
+pub fn enable(rcc: &RegisterBlock) {
+    start(rcc, None);
+}
+
+/// Disables the HSI48
+///
+/// Make sure that no peripheral is using it anymore.
+pub fn disable(rcc: &RegisterBlock) {
+    rcc.crrcr.modify(|_, w| w.hsi48on().clear_bit());
+}
+
+/// Checks if the HSI48 is running
+pub fn is_ready(rcc: &RegisterBlock) -> bool {
+    rcc.crrcr.read().hsi48rdy().bit_is_set()
+}
+
+pub(super) fn start(rcc: &RegisterBlock, wait_hook: Option<WaitHook>) {
+    rcc.crrcr.modify(|_, w| w.hsi48on().set_bit());
+    wait_until(wait_hook, || is_ready(rcc));
+}
@@ -0,0 +1,162 @@
+//! Clocks that run during Stop and the clocks the MCU runs on after leaving Stop and Standby
+
+use crate::pac::rcc::RegisterBlock;
+
+use super::{Clocks, MsiFreq};
+
+/// Oscillators and PLLs that peripheral clocks are derived from, see `Clocks::survives_stop`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RootClock {
+    MSI,
+    HSI16,
+    HSI48,
+    HSE,
+    /// The main PLL or one of the PLLSAIs
+    PLL,
+    LSE,
+    LSI,
+}
+
+/// Low power modes the MCU can be woken up from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LowPowerMode {
+    Stop0,
+    Stop1,
+    Stop2,
+    Standby,
+    Shutdown,
+}
+
+/// Clock SYSCLK is switched to when waking up from Stop (`RCC_CFGR.STOPWUCK`)
+///
+/// The MSI keeps the range of `RCC_CR.MSIRANGE`, so after the wakeup it runs at the range it had
+/// before entering Stop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopWakeupClock {
+    MSI,
+    HSI16,
+}
+
+/// MSI range used after a wakeup from Standby (`RCC_CSR.MSISRANGE`)
+///
+/// Only the ranges from 1 MHz to 8 MHz are supported by the hardware.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MsiStandbyRange {
+    RANGE1M = 4,
+    RANGE2M = 5,
+    RANGE4M = 6,
+    RANGE8M = 7,
+}
+
+impl MsiStandbyRange {
+    /// Returns the MSI range this corresponds to
+    pub fn to_msi_freq(self) -> MsiFreq {
+        match self {
+            Self::RANGE1M => MsiFreq::RANGE1M,
+            Self::RANGE2M => MsiFreq::RANGE2M,
+            Self::RANGE4M => MsiFreq::RANGE4M,
+            Self::RANGE8M => MsiFreq::RANGE8M,
+        }
+    }
+
+    // Reserved values read back as the 4 MHz reset value
+    fn from_bits(bits: u8) -> Self {
+        match bits {
+            4 => Self::RANGE1M,
+            5 => Self::RANGE2M,
+            7 => Self::RANGE8M,
+            _ => Self::RANGE4M,
+        }
+    }
+}
+
+/// Clocks after each low power mode exit, see `CFGR::set_low_power_clock_plan`
+///
+/// The default matches the reset state: MSI after Stop, MSI at 4 MHz after Standby. A wakeup
+/// from Shutdown always starts on the MSI at 4 MHz.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LowPowerClockPlan {
+    /// SYSCLK source after Stop
+    pub stop_wakeup: StopWakeupClock,
+    /// MSI range SYSCLK runs on after Standby
+    pub standby_msi: MsiStandbyRange,
+}
+
+impl Default for LowPowerClockPlan {
+    fn default() -> Self {
+        Self {
+            stop_wakeup: StopWakeupClock::MSI,
+            standby_msi: MsiStandbyRange::RANGE4M,
+        }
+    }
+}
+
+impl LowPowerClockPlan {
+    pub(super) fn freeze(&self, rcc: &RegisterBlock) {
+        rcc.cfgr
+            .modify(|_, w| w.stopwuck().bit(self.stop_wakeup == StopWakeupClock::HSI16));
+        // MSISRANGE can only be written while `RCC_CR.MSIRGSEL` is set. `freeze` applies the plan
+        // once the MSI is either off or configured, which already set MSIRGSEL, so setting it
+        // here never changes the range of a running MSI.
+        rcc.cr.modify(|_, w| w.msirgsel().set_bit());
+        rcc.csr
+            .modify(|_, w| unsafe { w.msisrange().bits(self.standby_msi as u8) });
+    }
+
+    pub(super) fn from_hardware(rcc: &RegisterBlock) -> Self {
+        Self {
+            stop_wakeup: if rcc.cfgr.read().stopwuck().bit_is_set() {
+                StopWakeupClock::HSI16
+            } else {
+                StopWakeupClock::MSI
+            },
+            standby_msi: MsiStandbyRange::from_bits(rcc.csr.read().msisrange().bits()),
+        }
+    }
+}
+
+/// Analog peripherals whose clock is switched off in Sleep mode, see
+/// `CFGR::gate_analog_in_sleep`
+///
+/// The ADCs and DACs draw a significant current while clocked. Gating them in Sleep mode is
+/// fine as long as they don't convert while the core sleeps, e.g. triggered by a timer. The
+/// default keeps their clocks running, which is the reset state.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AnalogSleepGating {
+    /// Gate the ADCs (`RCC_AHB2SMENR.ADCSMEN`)
+    pub adc: bool,
+    /// Gate the DACs (`RCC_APB1SMENR1.DAC1SMEN`), if the device has any
+    pub dac: bool,
+}
+
+impl AnalogSleepGating {
+    /// Gates all analog peripherals
+    pub const ALL: Self = Self {
+        adc: true,
+        dac: true,
+    };
+
+    pub(super) fn freeze(&self, rcc: &RegisterBlock) {
+        rcc.ahb2smenr.modify(|_, w| w.adcfssmen().bit(!self.adc));
+        // The L41x/L42x parts have no DAC
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        rcc.apb1smenr1.modify(|_, w| w.dac1smen().bit(!self.dac));
+    }
+}
+
+impl Clocks {
+    /// Returns true if `source` keeps running in Stop mode
+    ///
+    /// Only the LSE and LSI do, if they are enabled, and the HSI16 if it is kept on for the
+    /// peripherals with `CFGR::keep_hsi16_in_stop`. The HSE, HSI48 and the PLLs are stopped by the
+    /// hardware, and so is the MSI, which only comes back as wakeup clock. The HSI16 can
+    /// additionally be started on demand by some peripherals, see `UsartClockSource::runs_in_stop`.
+    pub fn survives_stop(&self, source: RootClock) -> bool {
+        match source {
+            RootClock::LSE => self.lse,
+            RootClock::LSI => self.lsi,
+            RootClock::HSI16 => self.hsi16 && self.hsi16_in_stop,
+            RootClock::MSI | RootClock::HSI48 | RootClock::HSE | RootClock::PLL => false,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Microcontroller clock output (MCO)
+
+use crate::gpio::Speed;
+use crate::time::Hertz;
+
+use super::{Clocks, HSI16_FREQ};
+
+/// Clock routed to the MCO pin (`RCC_CFGR.MCOSEL`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum McoSource {
+    SYSCLK = 0b001,
+    MSI = 0b010,
+    HSI16 = 0b011,
+    HSE = 0b100,
+    /// R output of the main PLL
+    PLL = 0b101,
+    LSI = 0b110,
+    LSE = 0b111,
+}
+
+impl McoSource {
+    /// Maps the value of `RCC_CFGR.MCOSEL` back to the source, `None` means the output is off
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        Some(match bits & 0b111 {
+            0b001 => Self::SYSCLK,
+            0b010 => Self::MSI,
+            0b011 => Self::HSI16,
+            0b100 => Self::HSE,
+            0b101 => Self::PLL,
+            0b110 => Self::LSI,
+            0b111 => Self::LSE,
+            _ => return None,
+        })
+    }
+
+    /// Returns the frequency of the source, if it is running
+    pub fn freq(self, clocks: &Clocks) -> Option<Hertz> {
+        match self {
+            Self::SYSCLK => Some(clocks.sysclk),
+            Self::MSI => clocks.msi_hz(),
+            Self::HSI16 => clocks.hsi16.then_some(HSI16_FREQ),
+            Self::HSE => clocks.hse,
+            Self::PLL => clocks.pll,
+            Self::LSI => clocks.lsi_hz(),
+            Self::LSE => clocks.lse_hz(),
+        }
+    }
+}
+
+/// Divider between the source and the MCO pin (`RCC_CFGR.MCOPRE`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum McoPrescaler {
+    Div1 = 0b000,
+    Div2 = 0b001,
+    Div4 = 0b010,
+    Div8 = 0b011,
+    Div16 = 0b100,
+}
+
+impl McoPrescaler {
+    /// Maps the value of `RCC_CFGR.MCOPRE` back to the prescaler, if the value is not reserved
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        Some(match bits {
+            0b000 => Self::Div1,
+            0b001 => Self::Div2,
+            0b010 => Self::Div4,
+            0b011 => Self::Div8,
+            0b100 => Self::Div16,
+            _ => return None,
+        })
+    }
+
+    pub fn div_factor(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+impl Clocks {
+    pub(super) fn mco_from_bits(&self, mcosel: u8, mcopre: u8) -> Option<Hertz> {
+        let source = McoSource::from_bits(mcosel)?;
+        let prescaler = McoPrescaler::from_bits(mcopre)?;
+
+        Some(source.freq(self)? / prescaler.div_factor())
+    }
+
+    /// Returns the frequency at the MCO pin, `None` if the output is off or its source doesn't
+    /// run
+    pub fn mco_freq(&self) -> Option<Hertz> {
+        self.mco
+    }
+
+    /// Returns the slowest GPIO speed the MCO pin can be configured with without distorting the
+    /// output, `None` if the output is off
+    ///
+    /// The limits are the maximum I/O frequencies of the STM32L4 datasheets at 50 pF and
+    /// VDD ≥ 2.7 V: 5 MHz for low, 25 MHz for medium and 50 MHz for high speed. Lower supply
+    /// voltages or higher loads reduce them, and outputs above 50 MHz are only usable on very
+    /// high speed pins with a light load.
+    pub fn mco_pin_speed(&self) -> Option<Speed> {
+        let freq = self.mco?;
+
+        Some(if freq <= Hertz::MHz(5) {
+            Speed::Low
+        } else if freq <= Hertz::MHz(25) {
+            Speed::Medium
+        } else if freq <= Hertz::MHz(50) {
+            Speed::High
+        } else {
+            Speed::VeryHigh
+        })
+    }
+}
@@ -0,0 +1,61 @@
+//! Runtime measurement of the internal oscillators against an accurate timer clock
+
+use core::convert::TryFrom;
+
+use crate::time::Hertz;
+
+use super::Clocks;
+
+/// Result of measuring an oscillator with a timer input capture
+///
+/// The LSI can be routed to the input capture of TIM16 and the MSI to the one of TIM17 (see
+/// `TIMx_OR1.TI1_RMP`). With the timer running on an accurate clock, e.g. derived from the HSE,
+/// `ticks` timer counts between captures `periods` oscillator periods apart give the oscillator
+/// frequency. Capturing over several periods, e.g. with the input capture prescaler, improves the
+/// resolution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CaptureRatio {
+    /// Clock the timer counts with, e.g. `Clocks::timclk2`
+    pub timer_clk: Hertz,
+    /// Timer counts between the captures
+    pub ticks: u32,
+    /// Oscillator periods between the captures
+    pub periods: u32,
+}
+
+impl CaptureRatio {
+    /// Returns the measured frequency, rounded to the nearest Hz
+    ///
+    /// `None` if `ticks` or `periods` is zero or the result doesn't fit in `Hertz`.
+    pub fn freq(&self) -> Option<Hertz> {
+        if self.ticks == 0 || self.periods == 0 {
+            return None;
+        }
+
+        let ticks = u64::from(self.ticks);
+        let freq = (u64::from(self.timer_clk.raw()) * u64::from(self.periods) + ticks / 2) / ticks;
+        u32::try_from(freq).ok().map(Hertz::Hz)
+    }
+}
+
+impl Clocks {
+    /// Records the LSI frequency measured with `ratio`, to be returned by `lsi_hz`
+    ///
+    /// Returns the measured frequency, `None` if the measurement is invalid, in which case
+    /// nothing is recorded.
+    pub fn calibrate_lsi(&mut self, ratio: CaptureRatio) -> Option<Hertz> {
+        let freq = ratio.freq()?;
+        self.lsi_measured = Some(freq);
+        Some(freq)
+    }
+
+    /// Records the MSI frequency measured with `ratio`, to be returned by `msi_hz`
+    ///
+    /// Returns the measured frequency, `None` if the measurement is invalid, in which case
+    /// nothing is recorded. The frequencies derived from the MSI, like SYSCLK, are not updated.
+    pub fn calibrate_msi(&mut self, ratio: CaptureRatio) -> Option<Hertz> {
+        let freq = ratio.freq()?;
+        self.msi_measured = Some(freq);
+        Some(freq)
+    }
+}
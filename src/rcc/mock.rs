@@ -0,0 +1,179 @@
+//! Register stand-ins for the host tests of the clock setup
+//!
+//! The RCC, flash and PWR registers live in RAM, one set per test thread. Status bits don't
+//! follow the enables by themselves, `poll`, used as wait hook, plays the hardware instead: an
+//! oscillator or PLL that is switched on reads as not ready `READY_AFTER` times, one that is
+//! switched off reads as stopped on the next poll, and `RCC_CFGR.SWS` follows `RCC_CFGR.SW`.
+//! Everything the emulated hardware does is recorded as an `Event`.
+
+extern crate std;
+
+use std::boxed::Box;
+use std::cell::RefCell;
+use std::vec::Vec;
+
+use crate::pac::rcc::RegisterBlock;
+use crate::stm32::{flash, pwr};
+
+/// Number of unsuccessful polls before an oscillator or PLL that has been switched on is ready
+pub(super) const READY_AFTER: u32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Osc {
+    Msi,
+    Hsi16,
+    Hse,
+    Pll,
+    #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+    PllSai1,
+    Lsi,
+    Lse,
+    #[cfg(not(any(
+        feature = "stm32l471",
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486"
+    )))]
+    Hsi48,
+}
+
+/// Something the emulated hardware did in reaction to a poll
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Event {
+    Ready(Osc),
+    Stopped(Osc),
+    /// SYSCLK has been switched to the source with the given SW bits, at the given flash latency
+    Switched {
+        sw: u8,
+        latency: u8,
+    },
+}
+
+/// Registers `CFGR::freeze` works on
+#[derive(Clone, Copy)]
+pub(super) struct Registers {
+    pub rcc: &'static RegisterBlock,
+    pub acr: &'static flash::ACR,
+    pub pwr_cr1: &'static pwr::CR1,
+}
+
+#[derive(Default)]
+struct Hardware {
+    polls: u32,
+    // Unsuccessful polls per oscillator since it has been switched on
+    starting: [u32; 8],
+    events: Vec<Event>,
+}
+
+impl Hardware {
+    // Returns the new register value if the ready bit of `osc` changes
+    fn step(&mut self, osc: Osc, bits: u32, on: u8, rdy: u8) -> Option<u32> {
+        let count = &mut self.starting[osc as usize];
+        match (bits & 1 << on != 0, bits & 1 << rdy != 0) {
+            (true, false) => {
+                *count += 1;
+                if *count < READY_AFTER {
+                    return None;
+                }
+                *count = 0;
+                self.events.push(Event::Ready(osc));
+                Some(bits | 1 << rdy)
+            }
+            (false, true) => {
+                self.events.push(Event::Stopped(osc));
+                Some(bits & !(1 << rdy))
+            }
+            _ => {
+                *count = 0;
+                None
+            }
+        }
+    }
+}
+
+std::thread_local! {
+    static REGISTERS: Registers = Registers::at_reset();
+    static HARDWARE: RefCell<Hardware> = RefCell::new(Hardware::default());
+}
+
+impl Registers {
+    fn at_reset() -> Self {
+        // NOTE(unsafe) the register blocks only hold plain `u32` cells, for which zero is valid
+        let rcc: &'static RegisterBlock = Box::leak(Box::new(unsafe { core::mem::zeroed() }));
+        let flash: &'static flash::RegisterBlock =
+            Box::leak(Box::new(unsafe { core::mem::zeroed() }));
+        let pwr: &'static pwr::RegisterBlock = Box::leak(Box::new(unsafe { core::mem::zeroed() }));
+
+        // The MSI runs at 4 MHz and drives SYSCLK, voltage range 1 is selected
+        rcc.cr
+            .write(|w| unsafe { w.bits(6 << 4 | 1 << 1 | 1 << 0) });
+        rcc.csr.write(|w| unsafe { w.msisrange().bits(6) });
+        pwr.cr1.write(|w| unsafe { w.vos().bits(0b01) });
+
+        Self {
+            rcc,
+            acr: &flash.acr,
+            pwr_cr1: &pwr.cr1,
+        }
+    }
+}
+
+/// Returns the registers of the current test, in their reset state on first use
+pub(super) fn registers() -> Registers {
+    REGISTERS.with(|registers| *registers)
+}
+
+/// Returns and clears the events recorded so far
+pub(super) fn take_events() -> Vec<Event> {
+    HARDWARE.with(|hardware| core::mem::take(&mut hardware.borrow_mut().events))
+}
+
+/// Returns the number of unsuccessful polls so far
+pub(super) fn polls() -> u32 {
+    HARDWARE.with(|hardware| hardware.borrow().polls)
+}
+
+macro_rules! emulate {
+    ($hardware:ident, $reg:expr, $osc:expr, $on:literal, $rdy:literal) => {
+        if let Some(bits) = $hardware.step($osc, $reg.read().bits(), $on, $rdy) {
+            $reg.write(|w| unsafe { w.bits(bits) });
+        }
+    };
+}
+
+/// Wait hook that advances the emulated hardware by one poll
+pub(super) fn poll() {
+    let Registers { rcc, acr, .. } = registers();
+    HARDWARE.with(|hardware| {
+        let mut hardware = hardware.borrow_mut();
+        let hardware = &mut *hardware;
+        hardware.polls += 1;
+
+        emulate!(hardware, rcc.cr, Osc::Msi, 0, 1);
+        emulate!(hardware, rcc.cr, Osc::Hsi16, 8, 10);
+        emulate!(hardware, rcc.cr, Osc::Hse, 16, 17);
+        emulate!(hardware, rcc.cr, Osc::Pll, 24, 25);
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        emulate!(hardware, rcc.cr, Osc::PllSai1, 26, 27);
+        emulate!(hardware, rcc.csr, Osc::Lsi, 0, 1);
+        emulate!(hardware, rcc.bdcr, Osc::Lse, 0, 1);
+        #[cfg(not(any(
+            feature = "stm32l471",
+            feature = "stm32l475",
+            feature = "stm32l476",
+            feature = "stm32l486"
+        )))]
+        emulate!(hardware, rcc.crrcr, Osc::Hsi48, 0, 1);
+
+        let cfgr = rcc.cfgr.read().bits();
+        let sw = (cfgr & 0b11) as u8;
+        if (cfgr >> 2) & 0b11 != sw as u32 {
+            rcc.cfgr
+                .write(|w| unsafe { w.bits(cfgr & !(0b11 << 2) | (sw as u32) << 2) });
+            hardware.events.push(Event::Switched {
+                sw,
+                latency: acr.read().latency().bits(),
+            });
+        }
+    });
+}
@@ -2,6 +2,39 @@ use crate::pac::rcc::RegisterBlock;
 use crate::time::Hertz;
 use fugit::RateExtU32;
 
+use super::{ensure_not_sysclk, wait_until, ClockError, SysclkSource, WaitHook};
+
+/// Switches the MSI off
+///
+/// Fails without touching the MSI if it feeds SYSCLK, directly or through the PLL.
+pub fn disable(rcc: &RegisterBlock) -> Result<(), ClockError> {
+    stop(rcc, None)
+}
+
+// `disable`, polling through the wait hook of `CFGR::freeze`
+pub(super) fn stop(rcc: &RegisterBlock, wait_hook: Option<WaitHook>) -> Result<(), ClockError> {
+    ensure_not_sysclk(rcc, SysclkSource::MSI)?;
+
+    rcc.cr
+        .modify(|_, w| w.msion().clear_bit().msipllen().clear_bit());
+    wait_until(wait_hook, || rcc.cr.read().msirdy().bit_is_clear());
+
+    Ok(())
+}
+
+/// MSI range and trim value that approximate a target frequency, see `MsiFreq::trimmed_for`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MsiTrim {
+    /// MSI range closest to the target
+    pub range: MsiFreq,
+    /// Value for `RCC_ICSCR.MSITRIM`, which is added to the factory calibration
+    pub trim: i8,
+    /// Estimated frequency with the trim applied
+    pub freq: Hertz,
+    /// Estimated difference between the achieved and the targeted frequency in Hz
+    pub error: i32,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MsiFreq {
     #[doc = "range 0 around 100 kHz"]
@@ -31,6 +64,43 @@ pub enum MsiFreq {
 }
 
 impl MsiFreq {
+    /// Maps the value of an MSI range field back to the frequency, if the value is valid
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        Some(match bits {
+            0 => Self::RANGE100K,
+            1 => Self::RANGE200K,
+            2 => Self::RANGE400K,
+            3 => Self::RANGE800K,
+            4 => Self::RANGE1M,
+            5 => Self::RANGE2M,
+            6 => Self::RANGE4M,
+            7 => Self::RANGE8M,
+            8 => Self::RANGE16M,
+            9 => Self::RANGE24M,
+            10 => Self::RANGE32M,
+            11 => Self::RANGE48M,
+            _ => return None,
+        })
+    }
+
+    /// Reads the range the MSI is configured to, the inverse of `freeze`
+    ///
+    /// Depending on `MSIRGSEL` this is `MSIRANGE` or `MSISRANGE`, the range used after a wakeup
+    /// from Standby. This allows reconstructing the MSI state after a reset or low power mode.
+    ///
+    /// # Panics
+    /// This function panics if the selected range register holds a reserved value.
+    pub fn from_hardware(rcc: &RegisterBlock) -> Self {
+        // The PAC does not provide a reader for MSIRGSEL (bit 3)
+        let bits = if rcc.cr.read().bits() & (1 << 3) != 0 {
+            rcc.cr.read().msirange().bits()
+        } else {
+            rcc.csr.read().msisrange().bits()
+        };
+
+        Self::from_bits(bits).expect("MSI range register holds a reserved value")
+    }
+
     pub fn to_hertz(self) -> Hertz {
         (match self {
             Self::RANGE100K => 100_000,
@@ -49,26 +119,136 @@ impl MsiFreq {
         .Hz()
     }
 
-    pub fn freeze(self, rcc: &RegisterBlock, use_lse_calibration: bool) {
+    /// Returns the worst-case deviation below and above the nominal frequency in parts per million
+    ///
+    /// Without LSE calibration these are the drifts over temperature plus the drifts over supply
+    /// voltage from the STM32L4 datasheets. With LSE calibration (MSI PLL mode) the frequency
+    /// follows the LSE, and the bounds cover the ±0.25 % the hardware guarantees for USB. The
+    /// error of the LSE crystal itself is not included.
+    pub fn tolerance_ppm(self, lse_calibrated: bool) -> (u32, u32) {
+        if lse_calibrated {
+            return (2_500, 2_500);
+        }
+
+        match self as u8 {
+            0..=3 => (47_000, 35_000),
+            4..=7 => (105_000, 67_000),
+            _ => (200_000, 90_000),
+        }
+    }
+
+    /// Returns the minimum and maximum frequency of the range without LSE calibration
+    pub fn frequency_bounds(self) -> (Hertz, Hertz) {
+        self.bounds(false)
+    }
+
+    /// Returns the minimum and maximum frequency of the range while it is calibrated by the LSE,
+    /// see `Clocks::msi_is_calibrated`
+    pub fn calibrated_frequency_bounds(self) -> (Hertz, Hertz) {
+        self.bounds(true)
+    }
+
+    fn bounds(self, lse_calibrated: bool) -> (Hertz, Hertz) {
+        let (below, above) = self.tolerance_ppm(lse_calibrated);
+        let nominal = u64::from(self.to_hertz().raw());
+        let scale = |ppm: u64| (nominal * ppm / 1_000_000) as u32;
+
+        (
+            Hertz::Hz(scale(1_000_000 - u64::from(below))),
+            Hertz::Hz(scale(1_000_000 + u64::from(above))),
+        )
+    }
+
+    /// Nominal change of the MSI frequency per MSITRIM step in parts per million
+    ///
+    /// The actual step differs between parts and ranges, so trimmed frequencies are estimates that
+    /// should be verified, e.g. against the LSE.
+    pub const TRIM_STEP_PPM: u32 = 2_000;
+
+    /// Picks the MSI range closest to `target` and the trim value that nudges it towards `target`
+    pub fn trimmed_for(target: Hertz) -> MsiTrim {
+        let range = (0..=11)
+            .filter_map(Self::from_bits)
+            .min_by_key(|range| range.to_hertz().raw().abs_diff(target.raw()))
+            .unwrap_or(Self::RANGE4M);
+        let nominal = range.to_hertz().raw() as i64;
+
+        let step = nominal * Self::TRIM_STEP_PPM as i64;
+        let offset = (target.raw() as i64 - nominal) * 1_000_000;
+        // Round to the closest trim step
+        let trim =
+            ((offset + offset.signum() * step / 2) / step).clamp(i8::MIN as i64, i8::MAX as i64);
+
+        let freq = nominal + nominal * trim * Self::TRIM_STEP_PPM as i64 / 1_000_000;
+
+        MsiTrim {
+            range,
+            trim: trim as i8,
+            freq: (freq as u32).Hz(),
+            error: (freq - target.raw() as i64) as i32,
+        }
+    }
+
+    /// Switches the MSI on at this range and waits until it is ready
+    ///
+    /// All MSI range changes go through here. MSIRGSEL is always set along with the range, so the
+    /// range is taken from `RCC_CR.MSIRANGE` instead of `RCC_CSR.MSISRANGE`, which only applies
+    /// after a reset or standby. The range must not change while the MSI is on but not yet ready,
+    /// so a pending start-up is waited for first.
+    pub fn freeze(
+        self,
+        rcc: &RegisterBlock,
+        use_lse_calibration: bool,
+        wait_hook: Option<WaitHook>,
+    ) {
+        if rcc.cr.read().msion().bit_is_set() {
+            wait_until(wait_hook, || rcc.cr.read().msirdy().bit_is_set());
+        }
+
         unsafe {
             rcc.cr.modify(|_, w| {
                 w.msirange()
                     .bits(self as u8)
                     .msirgsel()
                     .set_bit()
+                    // Use LSE to automatically calibrate MSI
+                    .msipllen()
+                    .bit(use_lse_calibration)
                     .msion()
-                    .set_bit();
-
-                // Use LSE to automatically calibrate MSI
-                if use_lse_calibration {
-                    w.msipllen().set_bit();
-                }
-
-                w
+                    .set_bit()
             });
         }
 
         // Wait until MSI is running with the correct configuration
-        while rcc.cr.read().msirdy().bit_is_clear() {}
+        wait_until(wait_hook, || rcc.cr.read().msirdy().bit_is_set());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trimmed_for_rounds_negative_offsets_to_nearest_step() {
+        // 10 kHz below 4 MHz is 1.25 trim steps of 8 kHz, rounded to one step down
+        let trim = MsiFreq::trimmed_for(Hertz::kHz(3_990));
+        assert_eq!(trim.range, MsiFreq::RANGE4M);
+        assert_eq!(trim.trim, -1);
+        assert_eq!(trim.freq, Hertz::kHz(3_992));
+        assert_eq!(trim.error, 2_000);
+
+        // 1.75 steps round to two
+        assert_eq!(MsiFreq::trimmed_for(Hertz::kHz(3_986)).trim, -2);
+        assert_eq!(MsiFreq::trimmed_for(Hertz::kHz(4_014)).trim, 2);
+    }
+
+    #[test]
+    fn trimmed_for_clamps_trim_to_i8() {
+        // 49 % above 100 kHz would take 245 steps
+        let trim = MsiFreq::trimmed_for(Hertz::kHz(149));
+        assert_eq!(trim.range, MsiFreq::RANGE100K);
+        assert_eq!(trim.trim, i8::MAX);
+        assert_eq!(trim.freq, Hertz::Hz(125_400));
+        assert_eq!(trim.error, -23_600);
     }
 }
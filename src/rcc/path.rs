@@ -0,0 +1,101 @@
+//! Divider chain from SYSCLK to the bus clock of a peripheral, for clock tree debugging
+
+use crate::time::Hertz;
+
+use super::{Clocks, RccBus, AHB1, AHB2, AHB3, APB1R1, APB1R2, APB2};
+
+/// Bus clock a peripheral is connected to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusClock {
+    /// AHB clock (HCLK)
+    Hclk,
+    /// APB1 clock (PCLK1)
+    Pclk1,
+    /// APB2 clock (PCLK2)
+    Pclk2,
+}
+
+/// Bus register proxies and the clock of the bus
+pub trait GetBusClock {
+    const CLOCK: BusClock;
+}
+
+macro_rules! bus_clock {
+    ($($busX:ident => $clock:ident,)+) => {
+        $(
+            impl GetBusClock for $busX {
+                const CLOCK: BusClock = BusClock::$clock;
+            }
+        )+
+    };
+}
+
+bus_clock! {
+    AHB1 => Hclk,
+    AHB2 => Hclk,
+    AHB3 => Hclk,
+    APB1R1 => Pclk1,
+    APB1R2 => Pclk1,
+    APB2 => Pclk2,
+}
+
+/// Divider chain SYSCLK → HCLK (÷ HPRE) → PCLKx (÷ PPREx) → timer clock (× 2)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClockPath {
+    /// System clock at the start of the chain
+    pub sysclk: Hertz,
+    /// AHB prescaler
+    pub hpre: u32,
+    /// AHB clock
+    pub hclk: Hertz,
+    /// APB bus, APB prescaler and resulting APB clock, `None` for peripherals on the AHB
+    pub apb: Option<(BusClock, u8, Hertz)>,
+    /// Whether the timer clock is doubled, which is the case if the APB prescaler is not 1
+    pub timer_x2: bool,
+    /// Resulting clock of the peripheral
+    pub freq: Hertz,
+}
+
+impl Clocks {
+    /// Returns the divider chain from SYSCLK to the bus clock of the peripheral `PER`
+    pub fn clock_path<PER>(&self) -> ClockPath
+    where
+        PER: RccBus,
+        PER::Bus: GetBusClock,
+    {
+        self.path(<PER::Bus as GetBusClock>::CLOCK, false)
+    }
+
+    /// Returns the divider chain from SYSCLK to the kernel clock of the timer `TIM`
+    pub fn timer_clock_path<TIM>(&self) -> ClockPath
+    where
+        TIM: RccBus,
+        TIM::Bus: GetBusClock,
+    {
+        self.path(<TIM::Bus as GetBusClock>::CLOCK, true)
+    }
+
+    fn path(&self, clock: BusClock, timer: bool) -> ClockPath {
+        let apb = match clock {
+            BusClock::Hclk => None,
+            BusClock::Pclk1 => Some((clock, self.ppre1, self.pclk1)),
+            BusClock::Pclk2 => Some((clock, self.ppre2, self.pclk2)),
+        };
+        let timer_x2 = timer && matches!(apb, Some((_, ppre, _)) if ppre > 1);
+
+        let freq = match apb {
+            Some((_, _, pclk)) if timer_x2 => pclk * 2,
+            Some((_, _, pclk)) => pclk,
+            None => self.hclk,
+        };
+
+        ClockPath {
+            sysclk: self.sysclk,
+            hpre: self.sysclk.raw() / self.hclk.raw(),
+            hclk: self.hclk,
+            apb,
+            timer_x2,
+            freq,
+        }
+    }
+}
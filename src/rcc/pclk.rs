@@ -1,6 +1,8 @@
 use crate::pac::rcc::RegisterBlock;
 use crate::time::Hertz;
 
+use super::{Clocks, MAX_PCLK1, MAX_PCLK2, MAX_SYSCLK_RANGE1};
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Prescaler {
     Div1,
@@ -11,18 +13,56 @@ pub enum Prescaler {
 }
 
 impl Prescaler {
-    pub fn from_ratio(source: Hertz, target: Hertz) -> Self {
-        assert!(source.raw() % target.raw() == 0);
+    /// Returns the prescaler that derives `target` from `source`, if the hardware has one
+    pub fn try_from_ratio(source: Hertz, target: Hertz) -> Option<Self> {
+        if target.raw() == 0 || source.raw() % target.raw() != 0 {
+            return None;
+        }
 
         match source / target {
-	    0 => panic!("HCLK must not be 0. Unable to create APB prescaler."),
-	    1 => Self::Div1,
-            2 => Self::Div2,
-            4 => Self::Div4,
-            8 => Self::Div8,
-            16 => Self::Div16,
-	    _ => panic!("APB prescalers can only be set to a value that is HCLK divided by a power of 2 less or equals to 16")
-	}
+            1 => Some(Self::Div1),
+            2 => Some(Self::Div2),
+            4 => Some(Self::Div4),
+            8 => Some(Self::Div8),
+            16 => Some(Self::Div16),
+            _ => None,
+        }
+    }
+
+    /// Returns the APB clock for the timer clock `timclk`, if an available prescaler results in it
+    ///
+    /// The timers run at twice the APB clock, unless the prescaler is 1.
+    pub fn pclk_for_timclk(hclk: Hertz, timclk: Hertz) -> Option<Hertz> {
+        if timclk == hclk {
+            return Some(hclk);
+        }
+        if timclk.raw() % 2 != 0 {
+            return None;
+        }
+
+        let pclk = timclk / 2;
+        match Self::try_from_ratio(hclk, pclk)? {
+            Self::Div1 => None,
+            _ => Some(pclk),
+        }
+    }
+
+    #[track_caller]
+    pub fn from_ratio(source: Hertz, target: Hertz) -> Self {
+        assert!(target.raw() != 0, "The APB clock must not be 0 Hz");
+        Self::try_from_ratio(source, target).expect(
+            "APB prescalers can only be set to a value that is HCLK divided by a power of 2 less or equals to 16",
+        )
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        match bits {
+            0b100 => Self::Div2,
+            0b101 => Self::Div4,
+            0b110 => Self::Div8,
+            0b111 => Self::Div16,
+            _ => Self::Div1,
+        }
     }
 
     pub fn bits(self) -> u8 {
@@ -47,20 +87,83 @@ impl Prescaler {
 }
 
 macro_rules! pclk_config {
-    ($pclk:ident, $num:literal, $div_bits:ident) => {
+    ($pclk:ident, $num:literal, $div_bits:ident, $pclkX:ident, $timclkX:ident, $max:ident) => {
         #[derive(Copy, Clone)]
         pub struct $pclk {
             freq: Hertz,
+            min_prescaler: Prescaler,
         }
 
         impl $pclk {
             pub fn new(freq: Hertz) -> Self {
-                Self { freq }
+                Self {
+                    freq,
+                    min_prescaler: Prescaler::Div1,
+                }
+            }
+
+            /// Requires the APB prescaler to divide HCLK by at least `min`, for peripherals that
+            /// must not run with the prescaler set to 1
+            pub fn with_min_prescaler(mut self, min: Prescaler) -> Self {
+                self.min_prescaler = min;
+                self
+            }
+
+            pub fn freq(&self) -> Hertz {
+                self.freq
+            }
+
+            pub fn min_prescaler(&self) -> Prescaler {
+                self.min_prescaler
             }
 
             pub fn freeze(self, hclk_freq: Hertz, rcc: &RegisterBlock) -> (Hertz, Hertz) {
                 let divider = Prescaler::from_ratio(hclk_freq, self.freq);
+                assert!(
+                    self.freq <= $max,
+                    concat!("PCLK", $num, " exceeds ", stringify!($max))
+                );
+                assert!(
+                    divider.div_factor() >= self.min_prescaler.div_factor(),
+                    "APB prescaler is below the required minimum"
+                );
+
+                let (freq, timclk_freq) = self.program(divider, rcc);
+                // Doubled by a prescaler of at least 2, so bounded by HCLK as long as the
+                // prescaler matches the requested frequency
+                assert!(
+                    timclk_freq <= MAX_SYSCLK_RANGE1,
+                    "Timer clock of {} Hz exceeds the maximum SYSCLK",
+                    timclk_freq.raw()
+                );
+
+                (freq, timclk_freq)
+            }
+
+            /// Applies the prescaler against a (possibly new) HCLK and updates the bus clock, the
+            /// timer clock and the prescaler recorded in `clocks`
+            ///
+            /// The configuration can be applied any number of times, e.g. after HCLK changed.
+            pub fn apply(self, hclk_freq: Hertz, rcc: &RegisterBlock, clocks: &mut Clocks) {
+                (clocks.$pclkX, clocks.$timclkX) = self.freeze(hclk_freq, rcc);
+                clocks.$div_bits = Prescaler::from_ratio(hclk_freq, self.freq).div_factor() as u8;
+            }
+
+            // `apply` without the limit checks, which `CFGR::validate` runs before `CFGR::freeze`
+            // gets here
+            pub(super) fn apply_validated(
+                self,
+                hclk_freq: Hertz,
+                rcc: &RegisterBlock,
+                clocks: &mut Clocks,
+            ) {
+                let divider = Prescaler::from_ratio(hclk_freq, self.freq);
+                (clocks.$pclkX, clocks.$timclkX) = self.program(divider, rcc);
+                clocks.$div_bits = divider.div_factor() as u8;
+            }
 
+            // Writes the prescaler and returns the bus and timer clocks
+            fn program(self, divider: Prescaler, rcc: &RegisterBlock) -> (Hertz, Hertz) {
                 rcc.cfgr
                     .modify(|_, w| unsafe { w.$div_bits().bits(divider.bits()) });
 
@@ -75,5 +178,24 @@ macro_rules! pclk_config {
     };
 }
 
-pclk_config!(Pclk1Config, 1, ppre1);
-pclk_config!(Pclk2Config, 2, ppre2);
+pclk_config!(Pclk1Config, 1, ppre1, pclk1, timclk1, MAX_PCLK1);
+pclk_config!(Pclk2Config, 2, ppre2, pclk2, timclk2, MAX_PCLK2);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_from_ratio_rejects_zero_target() {
+        assert_eq!(
+            Prescaler::try_from_ratio(Hertz::MHz(80), Hertz::Hz(0)),
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The APB clock must not be 0 Hz")]
+    fn from_ratio_panics_on_zero_target() {
+        Prescaler::from_ratio(Hertz::MHz(80), Hertz::Hz(0));
+    }
+}
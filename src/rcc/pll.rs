@@ -1,9 +1,96 @@
+use crate::flash::ACR;
 use crate::pac::rcc::RegisterBlock;
-use crate::rcc::{HSI16_FREQ, MAX_CLOCK_SPEED};
-use crate::time::Hertz;
+use crate::pac::RCC;
+use crate::pwr::{Pwr, VosRange};
+use crate::rcc::MAX_SYSCLK_RANGE1;
+use crate::time::{Hertz, MilliSeconds};
 use fugit::RateExtU32;
 
-use super::CFGR;
+use super::cfgr::flash_latency_bits;
+use super::{
+    ensure_not_sysclk, wait_until, wait_until_timeout, ClockError, Clocks, MsiFreq, SysclkSource,
+    WaitHook, HSI16_FREQ,
+};
+
+/// Returns true if the main PLL is locked (`RCC_CR.PLLRDY`)
+///
+/// This allows a supervision routine to detect a PLL that lost its lock at runtime.
+pub fn is_locked(rcc: &RegisterBlock) -> bool {
+    rcc.cr.read().pllrdy().bit_is_set()
+}
+
+/// Stops the main PLL
+///
+/// Fails without touching the PLL if it is the SYSCLK source.
+pub fn disable(rcc: &RegisterBlock) -> Result<(), ClockError> {
+    stop(rcc, None)
+}
+
+// `disable` with the wait hook of `CFGR::freeze`
+pub(super) fn stop(rcc: &RegisterBlock, wait_hook: Option<WaitHook>) -> Result<(), ClockError> {
+    ensure_not_sysclk(rcc, SysclkSource::PLL)?;
+
+    rcc.cr.modify(|_, w| w.pllon().clear_bit());
+    wait_until(wait_hook, || rcc.cr.read().pllrdy().bit_is_clear());
+
+    Ok(())
+}
+
+/// Restarts the main PLL after it lost its lock and waits up to `timeout` for it to lock again
+///
+/// The PLL keeps its configuration. `clocks` only provides the SYSCLK frequency the timeout is
+/// counted with. Fails without touching the PLL if it is the SYSCLK source, and with
+/// `ClockError::PllLockTimeout` if it does not lock in time, e.g. because its source is not
+/// running.
+pub fn relock(
+    rcc: &RegisterBlock,
+    clocks: &Clocks,
+    timeout: MilliSeconds,
+) -> Result<(), ClockError> {
+    disable(rcc)?;
+
+    rcc.cr.modify(|_, w| w.pllon().set_bit());
+    if wait_until_timeout(None, timeout, clocks.sysclk, || is_locked(rcc)) {
+        Ok(())
+    } else {
+        Err(ClockError::PllLockTimeout)
+    }
+}
+
+/// Output of the main PLL
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PllOutput {
+    /// P output, kernel clock of the SAIs
+    P,
+    /// Q output, 48 MHz clock
+    Q,
+    /// R output, SYSCLK
+    R,
+}
+
+/// Enables or disables a single output of the main PLL (`RCC_PLLCFGR.PLLxEN`)
+///
+/// The PLL keeps running and locked, so this can gate e.g. the 48 MHz clock during USB suspend
+/// while the R output drives SYSCLK. `Clocks` still reports the output frequencies set up by
+/// `freeze`. Fails without touching the PLL if the R output is disabled while the PLL is the
+/// SYSCLK source.
+pub fn set_output_enable(
+    rcc: &RegisterBlock,
+    output: PllOutput,
+    enable: bool,
+) -> Result<(), ClockError> {
+    if output == PllOutput::R && !enable {
+        ensure_not_sysclk(rcc, SysclkSource::PLL)?;
+    }
+
+    rcc.pllcfgr.modify(|_, w| match output {
+        PllOutput::P => w.pllpen().bit(enable),
+        PllOutput::Q => w.pllqen().bit(enable),
+        PllOutput::R => w.pllren().bit(enable),
+    });
+
+    Ok(())
+}
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum PllOutputDivider {
@@ -30,6 +117,15 @@ impl PllOutputDivider {
             Self::Div8 => 8,
         }
     }
+
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0b00 => Self::Div2,
+            0b01 => Self::Div4,
+            0b10 => Self::Div6,
+            _ => Self::Div8,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -51,14 +147,100 @@ impl PllSource {
             Self::HSE => 0b11,
         }
     }
+
+    /// Maps the value of `RCC_PLLCFGR.PLLSRC` back to the source, `None` means no clock
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        match bits & 0b11 {
+            0b01 => Some(Self::MSI),
+            0b10 => Some(Self::HSI16),
+            0b11 => Some(Self::HSE),
+            _ => None,
+        }
+    }
+}
+
+/// All fields of `RCC_PLLCFGR`, decoded into dividers and multipliers
+///
+/// `PllConfig::freeze` and `read_clocks` go through this. Writing it back with `to_register` is
+/// only allowed while the main PLL is off and, except on the L4+ parts, PLLSAI1 as well, which
+/// shares PLLM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PllCfgr {
+    /// Source shared by all PLLs, `None` means no clock
+    pub source: Option<PllSource>,
+    /// Input divider PLLM, 1 to 8 (1 to 16 on the L4+ parts)
+    pub m: u8,
+    /// VCO multiplier PLLN, 8 to 86 (8 to 127 on the L4+ parts)
+    pub n: u8,
+    /// P output divider selected by `RCC_PLLCFGR.PLLP`, 7 or 17
+    pub p: u8,
+    /// P output divider `RCC_PLLCFGR.PLLPDIV`, 2 to 31, overrides `p` unless 0
+    #[cfg(not(any(feature = "stm32l433", feature = "stm32l443", feature = "stm32l475")))]
+    pub pdiv: u8,
+    pub q: PllOutputDivider,
+    pub r: PllOutputDivider,
+    pub p_enable: bool,
+    pub q_enable: bool,
+    pub r_enable: bool,
+}
+
+impl PllCfgr {
+    /// Reads and decodes `RCC_PLLCFGR`
+    pub fn from_register(rcc: &RegisterBlock) -> Self {
+        let pllcfgr = rcc.pllcfgr.read();
+
+        Self {
+            source: PllSource::from_bits(pllcfgr.pllsrc().bits()),
+            m: pllcfgr.pllm().bits() + 1,
+            n: pllcfgr.plln().bits(),
+            p: if pllcfgr.pllp().bit_is_set() { 17 } else { 7 },
+            #[cfg(not(any(feature = "stm32l433", feature = "stm32l443", feature = "stm32l475")))]
+            pdiv: pllcfgr.pllpdiv().bits(),
+            q: PllOutputDivider::from_bits(pllcfgr.pllq().bits()),
+            r: PllOutputDivider::from_bits(pllcfgr.pllr().bits()),
+            p_enable: pllcfgr.pllpen().bit_is_set(),
+            q_enable: pllcfgr.pllqen().bit_is_set(),
+            r_enable: pllcfgr.pllren().bit_is_set(),
+        }
+    }
+
+    /// Encodes and writes all fields to `RCC_PLLCFGR`
+    pub fn to_register(&self, rcc: &RegisterBlock) {
+        rcc.pllcfgr.write(|w| unsafe {
+            w.pllsrc()
+                .bits(self.source.map_or(0b00, |source| source.source_bits()))
+                .pllm()
+                .bits(self.m - 1)
+                .plln()
+                .bits(self.n)
+                .pllp()
+                .bit(self.p == 17)
+                .pllq()
+                .bits(self.q.bits())
+                .pllr()
+                .bits(self.r.bits())
+                .pllpen()
+                .bit(self.p_enable)
+                .pllqen()
+                .bit(self.q_enable)
+                .pllren()
+                .bit(self.r_enable);
+            #[cfg(not(any(feature = "stm32l433", feature = "stm32l443", feature = "stm32l475")))]
+            w.pllpdiv().bits(self.pdiv);
+            w
+        });
+    }
 }
 
 pub struct PllConfig {
     source: PllSource,
+    msi_range: Option<MsiFreq>,
     target_freq: Hertz,
     in_div: u8,
     out_mul: u8,
     out_div: PllOutputDivider,
+    r_output: bool,
+    q_div: Option<PllOutputDivider>,
 }
 
 impl PllConfig {
@@ -73,66 +255,395 @@ impl PllConfig {
         assert!(in_div <= 8);
         assert!(out_mul >= 8);
         assert!(out_mul <= 86);
-        assert!(target_freq <= MAX_CLOCK_SPEED);
+        assert!(target_freq <= MAX_SYSCLK_RANGE1);
 
         Self {
             source,
+            msi_range: None,
             target_freq,
             in_div,
             out_mul,
             out_div,
+            r_output: true,
+            q_div: None,
+        }
+    }
+
+    /// Searches the PLL parameters that derive exactly `target_freq` at the R output from a
+    /// source running at `source_freq`
+    ///
+    /// Prefers the lowest input divider, i.e. the highest VCO input frequency, and then the lowest
+    /// VCO frequency. Returns `None` if no combination within the VCO limits hits the target.
+    pub fn autosetting(source: PllSource, source_freq: Hertz, target_freq: Hertz) -> Option<Self> {
+        if target_freq > MAX_SYSCLK_RANGE1 {
+            return None;
         }
+
+        for in_div in 1..=8 {
+            let vco_input = source_freq.raw() / in_div as u32;
+            if !(4_000_000..=16_000_000).contains(&vco_input) {
+                continue;
+            }
+            if let Some((out_mul, out_div)) = exact_multiplier(vco_input, target_freq) {
+                return Some(Self::new(source, target_freq, in_div, out_mul, out_div));
+            }
+        }
+
+        None
     }
 
+    /// PLL that only drives the Q output (PLL48M1CLK), e.g. for USB while SYSCLK runs on another
+    /// clock
+    ///
+    /// The R output is left disabled, so the PLL can't be selected as SYSCLK and there is no
+    /// PLLR frequency to match.
+    pub fn q_only(source: PllSource, in_div: u8, out_mul: u8, q_div: PllOutputDivider) -> Self {
+        assert!(in_div >= 1);
+        assert!(in_div <= 8);
+        assert!(out_mul >= 8);
+        assert!(out_mul <= 86);
+
+        Self {
+            source,
+            msi_range: None,
+            target_freq: Hertz::Hz(0),
+            in_div,
+            out_mul,
+            out_div: PllOutputDivider::Div2,
+            r_output: false,
+            q_div: Some(q_div),
+        }
+    }
+
+    /// Returns the targeted PLLR frequency, which is 0 Hz for a PLL created with `q_only`
     pub fn speed(&self) -> Hertz {
         self.target_freq
     }
 
-    pub fn freeze(&self, cfgr: &CFGR, rcc: &RegisterBlock) -> Hertz {
-        let clock_freq = match self.source {
-            PllSource::HSE => cfgr
-                .hse()
-                .expect("Please enable the HSE when selecting it as the PLL input clock")
-                .speed(),
-            PllSource::HSI16 => HSI16_FREQ,
-            PllSource::MSI => cfgr
-                .msi()
-                .expect("Please enable the MSI when selecting it as the PLL input clock")
-                .to_hertz(),
-        };
+    /// Returns the targeted PLLR frequency, if the R output is enabled
+    pub fn r_freq(&self) -> Option<Hertz> {
+        self.r_output.then_some(self.target_freq)
+    }
 
+    pub fn source(&self) -> PllSource {
+        self.source
+    }
+
+    /// Records the MSI range the PLL parameters have been calculated for
+    ///
+    /// `CFGR::validate` then rejects configurations where the MSI runs at a different range.
+    pub fn with_msi_range(mut self, range: MsiFreq) -> Self {
+        self.msi_range = Some(range);
+        self
+    }
+
+    pub fn msi_range(&self) -> Option<MsiFreq> {
+        self.msi_range
+    }
+
+    /// Enables the Q output (PLL48M1CLK), which can be selected as 48 MHz clock
+    pub fn with_q_output(mut self, div: PllOutputDivider) -> Self {
+        self.q_div = Some(div);
+        self
+    }
+
+    pub fn in_div(&self) -> u8 {
+        self.in_div
+    }
+
+    /// Frequency at the VCO input, i.e. the source clock divided by PLLM
+    pub fn vco_input_freq(&self, source_freq: Hertz) -> Hertz {
+        (source_freq.raw() / self.in_div as u32).Hz()
+    }
+
+    /// Frequency at the VCO output, i.e. the VCO input multiplied by PLLN
+    pub fn vco_freq(&self, source_freq: Hertz) -> Hertz {
+        (self.vco_input_freq(source_freq).raw() * self.out_mul as u32).Hz()
+    }
+
+    /// Frequency that is actually achieved at the PLLR output with the given source clock
+    ///
+    /// Unlike `speed`, which returns the targeted frequency, this is computed from the divider and
+    /// multiplier settings.
+    pub fn output_freq(&self, source_freq: Hertz) -> Hertz {
+        (self.vco_freq(source_freq).raw() / self.out_div.div_factor() as u32).Hz()
+    }
+
+    /// Frequency of the Q output, if it is enabled
+    pub fn q_freq(&self, source_freq: Hertz) -> Option<Hertz> {
+        let div = self.q_div?;
+        Some(self.vco_freq(source_freq) / div.div_factor() as u32)
+    }
+
+    /// Checks the VCO limits and, if the R output is enabled, that it matches the targeted
+    /// frequency
+    pub fn validate(&self, source_freq: Hertz) -> Result<(), ClockError> {
         // The clock frequency gets divided before it gets put into the PLL VCO input.
-        let source_freq: Hertz = (clock_freq.raw() / self.in_div as u32).Hz();
+        if !(Hertz::MHz(4)..=Hertz::MHz(16)).contains(&self.vco_input_freq(source_freq)) {
+            return Err(ClockError::PllVcoInputOutOfRange);
+        }
+        if !(Hertz::MHz(64)..=Hertz::MHz(344)).contains(&self.vco_freq(source_freq)) {
+            return Err(ClockError::PllVcoOutOfRange);
+        }
+        if self.r_output && self.output_freq(source_freq) != self.target_freq {
+            return Err(ClockError::PllFreqMismatch);
+        }
 
-        assert!(source_freq >= Hertz::MHz(4));
-        assert!(source_freq <= Hertz::MHz(16));
-        let vco_freq: Hertz = (source_freq.raw() * self.out_mul as u32).Hz();
-        assert!(vco_freq >= Hertz::MHz(64));
-        assert!(vco_freq <= Hertz::MHz(344));
-        let out_clock: Hertz = (vco_freq.raw() / self.out_div.div_factor() as u32).Hz();
-        assert!(out_clock <= MAX_CLOCK_SPEED);
+        Ok(())
+    }
 
-        assert_eq!(
-            out_clock, self.target_freq,
-            "PLL configuration parameters do not match the target frequency you want to achieve"
-        );
+    /// Starts the PLL with the given source clock frequency and returns the PLLR output frequency,
+    /// if the R output is enabled
+    ///
+    /// The source clock must already be running.
+    pub fn freeze(
+        &self,
+        clock_freq: Hertz,
+        rcc: &RegisterBlock,
+        wait_hook: Option<WaitHook>,
+    ) -> Option<Hertz> {
+        self.validate(clock_freq)
+            .expect("Invalid PLL configuration");
+        self.start(rcc, wait_hook)
+    }
 
-        // Enable on PLLR
-        rcc.pllcfgr.modify(|_, w| unsafe {
-            w.pllsrc()
-                .bits(self.source.source_bits())
-                .pllm()
-                .bits(self.in_div - 1)
-                .pllr()
-                .bits(self.out_div.bits())
-                .plln()
-                .bits(self.out_mul)
-        });
+    // Starts the PLL without checking the configuration first. Only for callers that already
+    // validated it, as `CFGR::validate` and `Clocks::set_pll` do.
+    pub(super) fn start(&self, rcc: &RegisterBlock, wait_hook: Option<WaitHook>) -> Option<Hertz> {
+        let mut pllcfgr = PllCfgr::from_register(rcc);
+        pllcfgr.source = Some(self.source);
+        pllcfgr.m = self.in_div;
+        pllcfgr.n = self.out_mul;
+        pllcfgr.r = self.out_div;
+        if let Some(div) = self.q_div {
+            pllcfgr.q = div;
+        }
+        pllcfgr.to_register(rcc);
 
         rcc.cr.modify(|_, w| w.pllon().set_bit());
-        while rcc.cr.read().pllrdy().bit_is_clear() {}
-        rcc.pllcfgr.modify(|_, w| w.pllren().set_bit());
+        wait_until(wait_hook, || rcc.cr.read().pllrdy().bit_is_set());
+
+        pllcfgr.r_enable = self.r_output;
+        pllcfgr.q_enable = self.q_div.is_some();
+        pllcfgr.to_register(rcc);
+
+        self.r_freq()
+    }
+}
+
+impl PllConfig {
+    // The PLLSAIs take their input from PLLSRC and, except on the L4+ parts, PLLM of
+    // `RCC_PLLCFGR`, so these must not change while one of them runs.
+    #[cfg_attr(
+        any(feature = "stm32l412", feature = "stm32l422"),
+        allow(unused_variables)
+    )]
+    fn check_shared_with_pllsai(&self, rcc: &RegisterBlock) -> Result<(), ClockError> {
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        {
+            let cr = rcc.cr.read();
+            #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9")))]
+            let pllsai_on = cr.pllsai1on().bit_is_set();
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9"))]
+            let pllsai_on = cr.pllsai1on().bit_is_set() || cr.pllsai2on().bit_is_set();
+
+            if pllsai_on {
+                let current = PllCfgr::from_register(rcc);
+                if current.source != Some(self.source) {
+                    return Err(ClockError::PllSourceConflict);
+                }
+                #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9")))]
+                if current.m != self.in_div {
+                    return Err(ClockError::PllInputDividerConflict);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Clocks {
+    /// Reprograms the PLL at runtime, e.g. to switch SYSCLK between 48 and 80 MHz, without running
+    /// `CFGR::freeze` again
+    ///
+    /// If the PLL drives SYSCLK, SYSCLK runs on the MSI while the PLL relocks and is switched back
+    /// afterwards. The AHB and APB prescalers are kept, so HCLK and the PCLKs scale with the PLL.
+    /// The flash latency is raised before and lowered after each SYSCLK switch. The returned
+    /// `Clocks` replace `self`.
+    ///
+    /// Fails without touching the hardware if the PLL source is not running, `new` doesn't fit it,
+    /// a running PLLSAI relies on the source or input divider shared with the PLL and `new`
+    /// changes them, or the PLL drives SYSCLK and `new` has no R output or exceeds the limit of
+    /// the voltage range.
+    pub fn set_pll(
+        &self,
+        new: PllConfig,
+        acr: &mut ACR,
+        pwr: &mut Pwr,
+    ) -> Result<Clocks, ClockError> {
+        let rcc = unsafe { &*RCC::ptr() };
+        let mut clocks = *self;
+
+        let source_freq = match new.source() {
+            PllSource::HSE => self.hse,
+            PllSource::HSI16 => self.hsi16.then_some(HSI16_FREQ),
+            PllSource::MSI => self.msi.map(|msi| msi.to_hertz()),
+        }
+        .ok_or(ClockError::PllSourceDisabled(new.source()))?;
+        new.validate(source_freq)?;
+        new.check_shared_with_pllsai(rcc)?;
+
+        if self.sysclk_source == SysclkSource::PLL {
+            if new.r_freq().is_none() {
+                return Err(ClockError::SysclkSourceDisabled(SysclkSource::PLL));
+            }
+            if new.speed() > pwr.max_sysclk() {
+                return Err(ClockError::SysclkAboveVoltageRange);
+            }
+        }
+
+        let range = pwr.power_range();
+        let hpre = self.sysclk.raw() / self.hclk.raw();
+        let bridge_hclk = if self.sysclk_source == SysclkSource::PLL {
+            // Bridge with the MSI, which is started at 4 MHz if it is off
+            let msi_freq = match self.msi {
+                Some(msi) => msi.to_hertz(),
+                None => {
+                    MsiFreq::RANGE4M.freeze(rcc, false, None);
+                    MsiFreq::RANGE4M.to_hertz()
+                }
+            };
+            switch_sysclk(
+                rcc,
+                acr,
+                range,
+                SysclkSource::MSI,
+                self.hclk,
+                msi_freq / hpre,
+            );
+
+            Some(msi_freq / hpre)
+        } else {
+            None
+        };
+
+        disable(rcc).expect("SYSCLK has been bridged with the MSI");
+        clocks.pll = new.start(rcc, None);
+        clocks.pll_q = new.q_freq(source_freq);
+        clocks.pll_source = Some(new.source());
+        clocks.pll_vco_input = Some(new.vco_input_freq(source_freq));
+        clocks.pll_vco_output = Some(new.vco_freq(source_freq));
+
+        if let Some(bridge_hclk) = bridge_hclk {
+            let hclk = new.speed() / hpre;
+            clocks.flash_latency =
+                switch_sysclk(rcc, acr, range, SysclkSource::PLL, bridge_hclk, hclk);
+
+            if self.msi.is_none() {
+                super::msi::disable(rcc).expect("the PLL does not run on the MSI");
+            }
+
+            clocks.sysclk = new.speed();
+            clocks.hclk = hclk;
+            clocks.pclk1 = hclk / clocks.ppre1 as u32;
+            clocks.pclk2 = hclk / clocks.ppre2 as u32;
+            clocks.timclk1 = clocks.pclk1 * if clocks.ppre1 > 1 { 2 } else { 1 };
+            clocks.timclk2 = clocks.pclk2 * if clocks.ppre2 > 1 { 2 } else { 1 };
+        }
+
+        Ok(clocks)
+    }
+}
+
+// Finds the VCO multiplier and output divider that derive exactly `target` from a VCO input
+// frequency of `vco_input` within the PLLN and VCO limits, preferring the lowest VCO frequency.
+pub(super) fn exact_multiplier(vco_input: u32, target: Hertz) -> Option<(u8, PllOutputDivider)> {
+    [
+        PllOutputDivider::Div2,
+        PllOutputDivider::Div4,
+        PllOutputDivider::Div6,
+        PllOutputDivider::Div8,
+    ]
+    .iter()
+    .find_map(|&out_div| {
+        let vco = target.raw() as u64 * out_div.div_factor() as u64;
+        let out_mul = vco / vco_input as u64;
+        (out_mul * vco_input as u64 == vco
+            && (8..=86).contains(&out_mul)
+            && (64_000_000..=344_000_000).contains(&vco))
+        .then_some((out_mul as u8, out_div))
+    })
+}
+
+// Switches SYSCLK with a flash latency that covers the HCLK before and after the switch, then
+// lowers it to what the new HCLK needs in the voltage range `range`. Returns the final latency.
+fn switch_sysclk(
+    rcc: &RegisterBlock,
+    acr: &mut ACR,
+    range: VosRange,
+    source: SysclkSource,
+    hclk_before: Hertz,
+    hclk_after: Hertz,
+) -> u8 {
+    let latency = flash_latency_bits(hclk_after, range);
+    let bridge_latency = latency.max(flash_latency_bits(hclk_before, range));
+
+    acr.acr()
+        .modify(|_, w| unsafe { w.latency().bits(bridge_latency) });
+    wait_until(None, || acr.acr().read().latency().bits() == bridge_latency);
 
-        out_clock
+    rcc.cfgr.modify(|_, w| unsafe { w.sw().bits(source as u8) });
+    wait_until(None, || rcc.cfgr.read().sws().bits() == source as u8);
+
+    acr.acr()
+        .modify(|_, w| unsafe { w.latency().bits(latency) });
+
+    latency
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn autosetting_derives_80mhz_from_hsi16() {
+        let pll = PllConfig::autosetting(PllSource::HSI16, HSI16_FREQ, Hertz::MHz(80)).unwrap();
+
+        // 16 MHz × 10 = 160 MHz VCO, divided by 2
+        assert_eq!(pll.in_div(), 1);
+        assert_eq!(pll.vco_freq(HSI16_FREQ), Hertz::MHz(160));
+        assert_eq!(pll.output_freq(HSI16_FREQ), Hertz::MHz(80));
+        assert_eq!(pll.validate(HSI16_FREQ), Ok(()));
+
+        // The 48 MHz for USB need a VCO of 192 MHz, at the lowest multiplier of at least 8
+        assert_eq!(
+            exact_multiplier(HSI16_FREQ.raw(), Hertz::MHz(48)),
+            Some((12, PllOutputDivider::Div4))
+        );
+    }
+
+    #[test]
+    fn autosetting_rejects_unreachable_targets() {
+        assert!(PllConfig::autosetting(PllSource::HSI16, HSI16_FREQ, Hertz::kHz(79_500)).is_none());
+        assert!(PllConfig::autosetting(PllSource::HSI16, HSI16_FREQ, Hertz::MHz(96)).is_none());
+        // A 2 MHz source can't be divided down to the 4 MHz minimum VCO input
+        assert!(PllConfig::autosetting(PllSource::HSE, Hertz::MHz(2), Hertz::MHz(64)).is_none());
+    }
+
+    #[test]
+    fn exact_multiplier_stays_within_vco_limits() {
+        // 64 MHz is the lowest VCO frequency, reached at the largest output divider
+        assert_eq!(
+            exact_multiplier(4_000_000, Hertz::MHz(8)),
+            Some((16, PllOutputDivider::Div8))
+        );
+        assert_eq!(exact_multiplier(4_000_000, Hertz::MHz(7)), None);
+        // 344 MHz is the highest VCO frequency, at the highest multiplier of 86
+        assert_eq!(
+            exact_multiplier(4_000_000, Hertz::MHz(172)),
+            Some((86, PllOutputDivider::Div2))
+        );
+        assert_eq!(exact_multiplier(16_000_000, Hertz::MHz(176)), None);
     }
 }
@@ -0,0 +1,120 @@
+//! PLLSAI1, whose Q output can provide the 48 MHz clock for USB, RNG and SDMMC
+
+use crate::pac::rcc::RegisterBlock;
+use crate::time::Hertz;
+use fugit::RateExtU32;
+
+use super::pll::{PllOutputDivider, PllSource};
+use super::{wait_until, ClockError, WaitHook};
+
+/// PLLSAI1 configuration for the 48 MHz clock
+///
+/// PLLSAI1 shares its source clock with the main PLL. Except on the L4+ parts it also shares the
+/// input divider (PLLM), so `in_div` has to match the one of the main PLL if that is enabled.
+pub struct PllSai1Config {
+    source: PllSource,
+    in_div: u8,
+    mul: u8,
+    q_div: Option<PllOutputDivider>,
+}
+
+impl PllSai1Config {
+    pub fn new(source: PllSource, in_div: u8, mul: u8) -> Self {
+        assert!(in_div >= 1);
+        assert!(in_div <= 8);
+        assert!(mul >= 8);
+        assert!(mul <= 86);
+
+        Self {
+            source,
+            in_div,
+            mul,
+            q_div: None,
+        }
+    }
+
+    /// Enables the Q output (PLL48M2CLK), which can be selected as 48 MHz clock
+    pub fn q_output(mut self, div: PllOutputDivider) -> Self {
+        self.q_div = Some(div);
+        self
+    }
+
+    pub fn source(&self) -> PllSource {
+        self.source
+    }
+
+    pub fn in_div(&self) -> u8 {
+        self.in_div
+    }
+
+    /// Frequency at the VCO output, i.e. the source clock divided by PLLM and multiplied by
+    /// PLLSAI1N
+    pub fn vco_freq(&self, source_freq: Hertz) -> Hertz {
+        (source_freq.raw() / self.in_div as u32 * self.mul as u32).Hz()
+    }
+
+    /// Frequency of the Q output, if it is enabled
+    pub fn q_freq(&self, source_freq: Hertz) -> Option<Hertz> {
+        let div = self.q_div?;
+        Some(self.vco_freq(source_freq) / div.div_factor() as u32)
+    }
+
+    /// Checks the VCO limits for the given source clock
+    pub fn validate(&self, source_freq: Hertz) -> Result<(), ClockError> {
+        let vco_input = source_freq / self.in_div as u32;
+        if !(Hertz::MHz(4)..=Hertz::MHz(16)).contains(&vco_input) {
+            return Err(ClockError::PllVcoInputOutOfRange);
+        }
+        if !(Hertz::MHz(64)..=Hertz::MHz(344)).contains(&self.vco_freq(source_freq)) {
+            return Err(ClockError::PllVcoOutOfRange);
+        }
+
+        Ok(())
+    }
+
+    /// Starts PLLSAI1 and returns the Q output frequency
+    ///
+    /// The PLL source (`RCC_PLLCFGR.PLLSRC`) and, except on the L4+ parts, the shared input
+    /// divider (`RCC_PLLCFGR.PLLM`) must already be set up.
+    pub fn freeze(
+        &self,
+        source_freq: Hertz,
+        rcc: &RegisterBlock,
+        wait_hook: Option<WaitHook>,
+    ) -> Option<Hertz> {
+        self.validate(source_freq)
+            .expect("Invalid PLLSAI1 configuration");
+        self.start(source_freq, rcc, wait_hook)
+    }
+
+    // `freeze` without the validation, for `CFGR::freeze`, whose `validate` covers PLLSAI1
+    pub(super) fn start(
+        &self,
+        source_freq: Hertz,
+        rcc: &RegisterBlock,
+        wait_hook: Option<WaitHook>,
+    ) -> Option<Hertz> {
+        // The configuration can only be changed while PLLSAI1 is off
+        rcc.cr.modify(|_, w| w.pllsai1on().clear_bit());
+        wait_until(wait_hook, || rcc.cr.read().pllsai1rdy().bit_is_clear());
+
+        rcc.pllsai1cfgr
+            .modify(|_, w| unsafe { w.pllsai1n().bits(self.mul) });
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9",))]
+        rcc.pllsai1cfgr
+            .modify(|_, w| unsafe { w.pllsai1m().bits(self.in_div - 1) });
+
+        if let Some(div) = self.q_div {
+            rcc.pllsai1cfgr
+                .modify(|_, w| unsafe { w.pllsai1q().bits(div.bits()) });
+        }
+
+        rcc.cr.modify(|_, w| w.pllsai1on().set_bit());
+        wait_until(wait_hook, || rcc.cr.read().pllsai1rdy().bit_is_set());
+
+        rcc.pllsai1cfgr
+            .modify(|_, w| w.pllsai1qen().bit(self.q_div.is_some()));
+
+        self.q_freq(source_freq)
+    }
+}
@@ -0,0 +1,166 @@
+//! PLLSAI2, which provides the display clocks (LTDC pixel clock and DSI) on the L4+ parts
+
+use crate::pac::rcc::RegisterBlock;
+use crate::time::Hertz;
+use fugit::RateExtU32;
+
+use super::pll::{PllOutputDivider, PllSource};
+use super::{wait_until, ClockError, WaitHook};
+
+/// Division of the PLLSAI2R output before it reaches the LTDC (`RCC_CCIPR2.PLLSAI2DIVR`)
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LtdcDivider {
+    Div2,
+    Div4,
+    Div8,
+    Div16,
+}
+
+impl LtdcDivider {
+    pub fn bits(self) -> u8 {
+        match self {
+            Self::Div2 => 0b00,
+            Self::Div4 => 0b01,
+            Self::Div8 => 0b10,
+            Self::Div16 => 0b11,
+        }
+    }
+
+    pub fn div_factor(self) -> u8 {
+        match self {
+            Self::Div2 => 2,
+            Self::Div4 => 4,
+            Self::Div8 => 8,
+            Self::Div16 => 16,
+        }
+    }
+}
+
+/// PLLSAI2 configuration for the display controller clocks
+///
+/// PLLSAI2 shares its source clock with the main PLL, but has its own input divider.
+pub struct PllSai2Config {
+    source: PllSource,
+    in_div: u8,
+    mul: u8,
+    dsi_div: Option<PllOutputDivider>,
+    ltdc_div: Option<(PllOutputDivider, LtdcDivider)>,
+}
+
+impl PllSai2Config {
+    pub fn new(source: PllSource, in_div: u8, mul: u8) -> Self {
+        assert!(in_div >= 1);
+        assert!(in_div <= 16);
+        assert!(mul >= 8);
+        assert!(mul <= 127);
+
+        Self {
+            source,
+            in_div,
+            mul,
+            dsi_div: None,
+            ltdc_div: None,
+        }
+    }
+
+    /// Enables the Q output (PLLDSICLK) and selects it as the DSI clock instead of the DSI PHY
+    pub fn dsi_output(mut self, div: PllOutputDivider) -> Self {
+        self.dsi_div = Some(div);
+        self
+    }
+
+    /// Enables the R output and routes it through `ltdc_div` to the LTDC pixel clock
+    pub fn ltdc_output(mut self, div: PllOutputDivider, ltdc_div: LtdcDivider) -> Self {
+        self.ltdc_div = Some((div, ltdc_div));
+        self
+    }
+
+    pub fn source(&self) -> PllSource {
+        self.source
+    }
+
+    /// Frequency at the VCO output, i.e. the source clock divided by PLLSAI2M and multiplied by
+    /// PLLSAI2N
+    pub fn vco_freq(&self, source_freq: Hertz) -> Hertz {
+        (source_freq.raw() / self.in_div as u32 * self.mul as u32).Hz()
+    }
+
+    /// Frequency of the DSI clock, if the DSI output is enabled
+    pub fn dsi_freq(&self, source_freq: Hertz) -> Option<Hertz> {
+        let div = self.dsi_div?;
+        Some(self.vco_freq(source_freq) / div.div_factor() as u32)
+    }
+
+    /// Frequency of the LTDC pixel clock, if the LTDC output is enabled
+    pub fn ltdc_freq(&self, source_freq: Hertz) -> Option<Hertz> {
+        let (div, ltdc_div) = self.ltdc_div?;
+        Some(self.vco_freq(source_freq) / (div.div_factor() as u32 * ltdc_div.div_factor() as u32))
+    }
+
+    /// Checks the VCO limits for the given source clock
+    pub fn validate(&self, source_freq: Hertz) -> Result<(), ClockError> {
+        let vco_input = source_freq / self.in_div as u32;
+        if !(Hertz::kHz(2_660)..=Hertz::MHz(16)).contains(&vco_input) {
+            return Err(ClockError::PllVcoInputOutOfRange);
+        }
+        if !(Hertz::MHz(64)..=Hertz::MHz(344)).contains(&self.vco_freq(source_freq)) {
+            return Err(ClockError::PllVcoOutOfRange);
+        }
+
+        Ok(())
+    }
+
+    /// Starts PLLSAI2 and returns the DSI and LTDC clock frequencies
+    ///
+    /// The PLL source (`RCC_PLLCFGR.PLLSRC`) must already be set up.
+    pub fn freeze(
+        &self,
+        source_freq: Hertz,
+        rcc: &RegisterBlock,
+        wait_hook: Option<WaitHook>,
+    ) -> (Option<Hertz>, Option<Hertz>) {
+        self.validate(source_freq)
+            .expect("Invalid PLLSAI2 configuration");
+        self.start(source_freq, rcc, wait_hook)
+    }
+
+    // `freeze` without the validation, for `CFGR::freeze`, whose `validate` covers PLLSAI2
+    pub(super) fn start(
+        &self,
+        source_freq: Hertz,
+        rcc: &RegisterBlock,
+        wait_hook: Option<WaitHook>,
+    ) -> (Option<Hertz>, Option<Hertz>) {
+        // The configuration can only be changed while PLLSAI2 is off
+        rcc.cr.modify(|_, w| w.pllsai2on().clear_bit());
+        wait_until(wait_hook, || rcc.cr.read().pllsai2rdy().bit_is_clear());
+
+        rcc.pllsai2cfgr
+            .modify(|_, w| unsafe { w.pllsai2m().bits(self.in_div - 1).pllsai2n().bits(self.mul) });
+
+        if let Some(div) = self.dsi_div {
+            rcc.pllsai2cfgr
+                .modify(|_, w| unsafe { w.pllsai2q().bits(div.bits()) });
+        }
+        if let Some((div, ltdc_div)) = self.ltdc_div {
+            rcc.pllsai2cfgr
+                .modify(|_, w| unsafe { w.pllsai2r().bits(div.bits()) });
+            rcc.ccipr2
+                .modify(|_, w| unsafe { w.pllsai2divr().bits(ltdc_div.bits()) });
+        }
+
+        rcc.cr.modify(|_, w| w.pllsai2on().set_bit());
+        wait_until(wait_hook, || rcc.cr.read().pllsai2rdy().bit_is_set());
+
+        rcc.pllsai2cfgr.modify(|_, w| {
+            w.pllsai2qen()
+                .bit(self.dsi_div.is_some())
+                .pllsai2ren()
+                .bit(self.ltdc_div.is_some())
+        });
+        rcc.ccipr2
+            .modify(|_, w| w.dsisel().bit(self.dsi_div.is_some()));
+
+        (self.dsi_freq(source_freq), self.ltdc_freq(source_freq))
+    }
+}
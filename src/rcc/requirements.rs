@@ -0,0 +1,131 @@
+//! Clock preconditions of drivers, checked against the frozen `Clocks`
+
+use crate::time::Hertz;
+
+use super::Clocks;
+
+/// Requirement that is not met by the clock configuration
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnmetRequirement {
+    /// SYSCLK is below the required minimum
+    SysclkTooLow,
+    /// SYSCLK is above the allowed maximum
+    SysclkTooHigh,
+    /// PCLK1 is not a multiple of the required frequency
+    Pclk1NotDivisible,
+    /// PCLK2 is not a multiple of the required frequency
+    Pclk2NotDivisible,
+    /// The LSE is not running
+    LseDisabled,
+    /// The LSI is not running
+    LsiDisabled,
+    /// The HSI16 is not running
+    Hsi16Disabled,
+    /// No 48 MHz clock is available
+    No48MHzClock,
+}
+
+/// Clock preconditions of a driver, see `Clocks::satisfies`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClockRequirements {
+    min_sysclk: Option<Hertz>,
+    max_sysclk: Option<Hertz>,
+    pclk1_divisible_by: Option<Hertz>,
+    pclk2_divisible_by: Option<Hertz>,
+    lse: bool,
+    lsi: bool,
+    hsi16: bool,
+    clk48: bool,
+}
+
+impl ClockRequirements {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires SYSCLK to run at least at `freq`
+    pub fn min_sysclk(mut self, freq: Hertz) -> Self {
+        self.min_sysclk = Some(freq);
+        self
+    }
+
+    /// Requires SYSCLK to run at most at `freq`
+    pub fn max_sysclk(mut self, freq: Hertz) -> Self {
+        self.max_sysclk = Some(freq);
+        self
+    }
+
+    /// Requires PCLK1 to be an exact multiple of `freq`, e.g. for a baud rate
+    pub fn pclk1_divisible_by(mut self, freq: Hertz) -> Self {
+        self.pclk1_divisible_by = Some(freq);
+        self
+    }
+
+    /// Requires PCLK2 to be an exact multiple of `freq`, e.g. for a baud rate
+    pub fn pclk2_divisible_by(mut self, freq: Hertz) -> Self {
+        self.pclk2_divisible_by = Some(freq);
+        self
+    }
+
+    /// Requires the LSE to run, e.g. for the RTC
+    pub fn lse(mut self) -> Self {
+        self.lse = true;
+        self
+    }
+
+    /// Requires the LSI to run
+    pub fn lsi(mut self) -> Self {
+        self.lsi = true;
+        self
+    }
+
+    /// Requires the HSI16 to run
+    pub fn hsi16(mut self) -> Self {
+        self.hsi16 = true;
+        self
+    }
+
+    /// Requires a 48 MHz clock for USB, RNG or SDMMC, see `Clocks::has_48mhz_clock`
+    pub fn clk48(mut self) -> Self {
+        self.clk48 = true;
+        self
+    }
+}
+
+impl Clocks {
+    /// Checks the clocks against the preconditions of a driver
+    pub fn satisfies(&self, requirements: &ClockRequirements) -> Result<(), UnmetRequirement> {
+        let divisible = |clock: Hertz, freq: Option<Hertz>| match freq {
+            Some(freq) => freq.raw() != 0 && clock.raw() % freq.raw() == 0,
+            None => true,
+        };
+
+        if matches!(requirements.min_sysclk, Some(min) if self.sysclk < min) {
+            return Err(UnmetRequirement::SysclkTooLow);
+        }
+        if matches!(requirements.max_sysclk, Some(max) if self.sysclk > max) {
+            return Err(UnmetRequirement::SysclkTooHigh);
+        }
+        if !divisible(self.pclk1, requirements.pclk1_divisible_by) {
+            return Err(UnmetRequirement::Pclk1NotDivisible);
+        }
+        if !divisible(self.pclk2, requirements.pclk2_divisible_by) {
+            return Err(UnmetRequirement::Pclk2NotDivisible);
+        }
+        if requirements.lse && !self.lse {
+            return Err(UnmetRequirement::LseDisabled);
+        }
+        if requirements.lsi && !self.lsi {
+            return Err(UnmetRequirement::LsiDisabled);
+        }
+        if requirements.hsi16 && !self.hsi16 {
+            return Err(UnmetRequirement::Hsi16Disabled);
+        }
+        if requirements.clk48 && !self.has_48mhz_clock() {
+            return Err(UnmetRequirement::No48MHzClock);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+//! Live on/ready state of the oscillators and PLLs, read straight from the RCC
+
+use crate::pac::rcc::RegisterBlock;
+
+/// On and ready flags of the oscillators and PLLs, see `oscillator_status`
+///
+/// Unlike `Clocks`, which describes the configuration applied by `freeze`, this reflects what is
+/// running right now, e.g. after a low-power transition.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OscStatus(u32);
+
+impl OscStatus {
+    pub const MSI_ON: Self = Self(1 << 0);
+    pub const MSI_READY: Self = Self(1 << 1);
+    pub const HSI16_ON: Self = Self(1 << 2);
+    pub const HSI16_READY: Self = Self(1 << 3);
+    pub const HSE_ON: Self = Self(1 << 4);
+    pub const HSE_READY: Self = Self(1 << 5);
+    pub const PLL_ON: Self = Self(1 << 6);
+    pub const PLL_READY: Self = Self(1 << 7);
+    pub const PLLSAI1_ON: Self = Self(1 << 8);
+    pub const PLLSAI1_READY: Self = Self(1 << 9);
+    pub const PLLSAI2_ON: Self = Self(1 << 10);
+    pub const PLLSAI2_READY: Self = Self(1 << 11);
+    pub const HSI48_ON: Self = Self(1 << 12);
+    pub const HSI48_READY: Self = Self(1 << 13);
+
+    /// Returns no flags
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns the raw flag bits
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are set
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true if no flag is set
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    fn set(&mut self, flag: Self, value: bool) {
+        if value {
+            self.0 |= flag.0;
+        }
+    }
+}
+
+impl core::ops::BitOr for OscStatus {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl core::ops::BitAnd for OscStatus {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+/// Reads the on and ready bits of all oscillators and PLLs from `RCC_CR` and `RCC_CRRCR`
+pub fn oscillator_status(rcc: &RegisterBlock) -> OscStatus {
+    let cr = rcc.cr.read();
+    let mut status = OscStatus::empty();
+
+    status.set(OscStatus::MSI_ON, cr.msion().bit_is_set());
+    status.set(OscStatus::MSI_READY, cr.msirdy().bit_is_set());
+    status.set(OscStatus::HSI16_ON, cr.hsion().bit_is_set());
+    status.set(OscStatus::HSI16_READY, cr.hsirdy().bit_is_set());
+    status.set(OscStatus::HSE_ON, cr.hseon().bit_is_set());
+    status.set(OscStatus::HSE_READY, cr.hserdy().bit_is_set());
+    status.set(OscStatus::PLL_ON, cr.pllon().bit_is_set());
+    status.set(OscStatus::PLL_READY, cr.pllrdy().bit_is_set());
+    status.set(OscStatus::PLLSAI1_ON, cr.pllsai1on().bit_is_set());
+    status.set(OscStatus::PLLSAI1_READY, cr.pllsai1rdy().bit_is_set());
+
+    #[cfg(any(
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    {
+        status.set(OscStatus::PLLSAI2_ON, cr.pllsai2on().bit_is_set());
+        status.set(OscStatus::PLLSAI2_READY, cr.pllsai2rdy().bit_is_set());
+    }
+
+    // The L47x/L48x parts have no HSI48
+    #[cfg(not(any(
+        feature = "stm32l471",
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486"
+    )))]
+    {
+        let crrcr = rcc.crrcr.read();
+        status.set(OscStatus::HSI48_ON, crrcr.hsi48on().bit_is_set());
+        status.set(OscStatus::HSI48_READY, crrcr.hsi48rdy().bit_is_set());
+    }
+
+    status
+}
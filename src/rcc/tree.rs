@@ -0,0 +1,159 @@
+//! The clock tree as nodes with their parent, for diagnostics and tooling
+
+use crate::time::Hertz;
+
+use super::ccipr::Clk48ClockSource;
+use super::pll::PllSource;
+use super::{Clocks, SysclkSource, HSI16_FREQ};
+
+/// Clock in the clock tree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockNode {
+    MSI,
+    HSI16,
+    HSI48,
+    HSE,
+    LSE,
+    LSI,
+    /// VCO of the main PLL
+    PllVco,
+    /// R output of the main PLL
+    PllR,
+    /// Q output of the main PLL
+    PllQ,
+    /// Q output of PLLSAI1
+    PllSai1Q,
+    SYSCLK,
+    HCLK,
+    PCLK1,
+    PCLK2,
+    /// Timer clock on APB1
+    TIMCLK1,
+    /// Timer clock on APB2
+    TIMCLK2,
+    /// 48 MHz clock for USB, RNG and SDMMC
+    CLK48,
+}
+
+/// Node of a `ClockTree`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClockTreeNode {
+    /// The clock
+    pub node: ClockNode,
+    /// Frequency, `None` if the clock doesn't run or is unknown
+    pub freq: Option<Hertz>,
+    /// Clock this one is derived from, `None` for oscillators
+    pub parent: Option<ClockNode>,
+}
+
+const NODES: usize = 17;
+
+/// Clock tree built from `Clocks`, see `Clocks::clock_tree`
+#[derive(Clone, Copy, Debug)]
+pub struct ClockTree {
+    nodes: [ClockTreeNode; NODES],
+}
+
+impl ClockTree {
+    /// Returns all nodes, including the ones that don't run
+    pub fn iter(&self) -> impl Iterator<Item = &ClockTreeNode> {
+        self.nodes.iter()
+    }
+
+    /// Returns the node of `node`
+    pub fn node(&self, node: ClockNode) -> &ClockTreeNode {
+        self.iter()
+            .find(|n| n.node == node)
+            .expect("every clock has a node")
+    }
+
+    /// Returns the nodes derived directly from `node`
+    pub fn children(&self, node: ClockNode) -> impl Iterator<Item = &ClockTreeNode> {
+        self.iter().filter(move |n| n.parent == Some(node))
+    }
+
+    /// Returns the ratio of a node to its parent as reduced multiplier and divider, e.g. `(1, 2)`
+    /// for a prescaler of 2
+    ///
+    /// `None` for oscillators and if either clock doesn't run.
+    pub fn ratio(&self, node: ClockNode) -> Option<(u32, u32)> {
+        let node = self.node(node);
+        let freq = node.freq?.raw();
+        let parent_freq = self.node(node.parent?).freq?.raw();
+
+        let gcd = gcd(freq, parent_freq);
+        Some((freq / gcd, parent_freq / gcd))
+    }
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+impl Clocks {
+    /// Builds the clock tree from the frozen configuration
+    pub fn clock_tree(&self) -> ClockTree {
+        let node = |node, freq, parent| ClockTreeNode { node, freq, parent };
+
+        let pll_parent = self.pll_source.map(|source| match source {
+            PllSource::MSI => ClockNode::MSI,
+            PllSource::HSI16 => ClockNode::HSI16,
+            PllSource::HSE => ClockNode::HSE,
+        });
+        let sysclk_parent = match self.sysclk_source {
+            SysclkSource::MSI => ClockNode::MSI,
+            SysclkSource::HSI16 => ClockNode::HSI16,
+            SysclkSource::HSE => ClockNode::HSE,
+            SysclkSource::PLL => ClockNode::PllR,
+        };
+        let clk48 = self.ccipr_snapshot().clk48;
+        let clk48_parent = clk48.source.map(|source| match source {
+            #[cfg(not(any(
+                feature = "stm32l471",
+                feature = "stm32l475",
+                feature = "stm32l476",
+                feature = "stm32l486"
+            )))]
+            Clk48ClockSource::HSI48 => ClockNode::HSI48,
+            #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+            Clk48ClockSource::PLLSAI1Q => ClockNode::PllSai1Q,
+            Clk48ClockSource::PLLQ => ClockNode::PllQ,
+            Clk48ClockSource::MSI => ClockNode::MSI,
+        });
+
+        ClockTree {
+            nodes: [
+                node(ClockNode::MSI, self.msi.map(|msi| msi.to_hertz()), None),
+                node(ClockNode::HSI16, self.hsi16.then_some(HSI16_FREQ), None),
+                node(ClockNode::HSI48, self.hsi48.then_some(Hertz::MHz(48)), None),
+                node(ClockNode::HSE, self.hse, None),
+                node(ClockNode::LSE, self.lse_hz(), None),
+                node(ClockNode::LSI, self.lsi_hz(), None),
+                node(ClockNode::PllVco, self.pll_vco_output, pll_parent),
+                node(ClockNode::PllR, self.pll, Some(ClockNode::PllVco)),
+                node(ClockNode::PllQ, self.pll_q, Some(ClockNode::PllVco)),
+                node(ClockNode::PllSai1Q, self.pllsai1_q, pll_parent),
+                node(ClockNode::SYSCLK, Some(self.sysclk), Some(sysclk_parent)),
+                node(ClockNode::HCLK, Some(self.hclk), Some(ClockNode::SYSCLK)),
+                node(ClockNode::PCLK1, Some(self.pclk1), Some(ClockNode::HCLK)),
+                node(ClockNode::PCLK2, Some(self.pclk2), Some(ClockNode::HCLK)),
+                node(
+                    ClockNode::TIMCLK1,
+                    Some(self.timclk1),
+                    Some(ClockNode::PCLK1),
+                ),
+                node(
+                    ClockNode::TIMCLK2,
+                    Some(self.timclk2),
+                    Some(ClockNode::PCLK2),
+                ),
+                node(ClockNode::CLK48, clk48.freq, clk48_parent),
+            ],
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Accuracy requirements of the 48 MHz clock for full-speed USB
+
+use crate::time::Hertz;
+
+use super::ccipr::Clk48ClockSource;
+use super::Clocks;
+
+/// Reason why the 48 MHz clock is not suitable for full-speed USB, see `Clocks::validate_usb`
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UsbClockError {
+    /// `RCC_CCIPR.CLK48SEL` holds a reserved value
+    NoClk48Source,
+    /// The clock selected as 48 MHz clock is not running
+    Clk48NotRunning(Clk48ClockSource),
+    /// The clock selected as 48 MHz clock does not run at 48 MHz
+    Clk48FreqMismatch(Hertz),
+    /// The MSI is selected, but not calibrated by the LSE, which is not accurate enough
+    MsiNotLseCalibrated,
+}
+
+impl Clocks {
+    /// Returns true if any clock that can be selected as 48 MHz clock runs at 48 MHz
+    ///
+    /// Candidates are the HSI48, the Q outputs of the main PLL and PLLSAI1 and the MSI, which only
+    /// counts if it is calibrated by the LSE. This doesn't look at the current `CLK48SEL`
+    /// selection, see `validate_usb` for the stricter check.
+    pub fn has_48mhz_clock(&self) -> bool {
+        let at_48mhz = |source: Clk48ClockSource| source.freq(self) == Some(Hertz::MHz(48));
+
+        #[cfg(not(any(
+            feature = "stm32l471",
+            feature = "stm32l475",
+            feature = "stm32l476",
+            feature = "stm32l486"
+        )))]
+        if at_48mhz(Clk48ClockSource::HSI48) {
+            return true;
+        }
+        #[cfg(not(any(feature = "stm32l412", feature = "stm32l422")))]
+        if at_48mhz(Clk48ClockSource::PLLSAI1Q) {
+            return true;
+        }
+
+        at_48mhz(Clk48ClockSource::PLLQ) || (self.msi_calibrated && at_48mhz(Clk48ClockSource::MSI))
+    }
+
+    /// Checks that the 48 MHz clock meets the ±0.25 % needed by full-speed USB
+    ///
+    /// Accepted are the HSI48, assuming it is trimmed by the CRS, the MSI at 48 MHz calibrated by
+    /// the LSE, and the Q output of the main PLL or PLLSAI1 at exactly 48 MHz. For the PLLs the
+    /// accuracy of their source is not checked, so they should run on the HSE.
+    pub fn validate_usb(&self) -> Result<(), UsbClockError> {
+        let clk48 = self.ccipr_snapshot().clk48;
+        let source = clk48.source.ok_or(UsbClockError::NoClk48Source)?;
+        let freq = clk48.freq.ok_or(UsbClockError::Clk48NotRunning(source))?;
+
+        if freq != Hertz::MHz(48) {
+            return Err(UsbClockError::Clk48FreqMismatch(freq));
+        }
+        if source == Clk48ClockSource::MSI && !self.msi_calibrated {
+            return Err(UsbClockError::MsiNotLseCalibrated);
+        }
+
+        Ok(())
+    }
+}
@@ -72,7 +72,7 @@ pub struct Rtc {
     rtc_config: RtcConfig,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u8)]
 pub enum RtcClockSource {
     /// 00: No clock
@@ -114,7 +114,7 @@ macro_rules! hal {
                             w.frxth().set_bit().ds().bits(0b111).ssoe().clear_bit()
                         });
 
-                    let br = Self::compute_baud_rate(clocks.$pclkX(), freq);
+                    let br = Self::compute_baud_rate(clocks.$pclkX().into(), freq);
 
                     // CPHA: phase
                     // CPOL: polarity
@@ -209,7 +209,7 @@ macro_rules! hal {
                 pub fn reclock(&mut self, freq: Hertz, clocks: Clocks) {
                     self.disable();
                     self.spi.cr1.modify(|_, w| unsafe {
-                        w.br().bits(Self::compute_baud_rate(clocks.$pclkX(), freq));
+                        w.br().bits(Self::compute_baud_rate(clocks.$pclkX().into(), freq));
                         w.spe().set_bit()
                     });
                 }
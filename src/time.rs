@@ -39,7 +39,7 @@ impl MonoTimer {
         drop(dwt);
 
         MonoTimer {
-            frequency: clocks.sysclk(),
+            frequency: Hertz::from(clocks.sysclk()),
         }
     }
 
@@ -150,7 +150,7 @@ macro_rules! hal {
                     <$TIM>::enable(apb);
                     <$TIM>::reset(apb);
 
-                    let clock = clocks.$timclk();
+                    let clock = Hertz::from(clocks.$timclk());
 
                     let mut timer = Timer {
                         clock,
@@ -175,7 +175,7 @@ macro_rules! hal {
                     <$TIM>::enable(apb);
                     <$TIM>::reset(apb);
 
-                    let clock = clocks.$timclk();
+                    let clock = Hertz::from(clocks.$timclk());
 
                     let psc = clock / frequency - 1;
 